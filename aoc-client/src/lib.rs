@@ -1,27 +1,35 @@
 use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Utc};
 use colored::{Color, Colorize};
 use dirs::{config_dir, home_dir};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use html2md::parse_html;
 use html2text::{
-    from_read, from_read_with_decorator,
-    render::text_renderer::TrivialDecorator,
+    from_read, from_read_rich, from_read_with_decorator,
+    render::text_renderer::{RichAnnotation, TrivialDecorator},
 };
 use http::StatusCode;
 use log::{debug, info, warn};
-use regex::Regex;
-use reqwest::blocking::Client as HttpClient;
+use regex::{Captures, Regex};
+use reqwest::blocking::{Client as HttpClient, RequestBuilder, Response};
 use reqwest::header::{
-    HeaderMap, HeaderValue, CONTENT_TYPE, COOKIE, USER_AGENT,
+    HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, USER_AGENT,
 };
 use reqwest::redirect::Policy;
-use serde::Deserialize;
-use std::cmp::{Ordering, Reverse};
-use std::collections::HashMap;
+use reqwest::{IntoUrl, Method};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fmt::{Display, Formatter};
-use std::fs::{read_to_string, OpenOptions};
-use std::io::Write;
+use std::fs::{create_dir_all, read_to_string, rename, File, OpenOptions};
+use std::io::{stdout, ErrorKind, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub type PuzzleYear = i32;
@@ -29,14 +37,19 @@ pub type PuzzleDay = u32;
 pub type LeaderboardId = u32;
 type MemberId = u64;
 type Score = u64;
+type Timestamp = i64;
 
-#[derive(Debug)]
+// Serialized as "part_one" and "part_two"
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PuzzlePart {
     PartOne,
     PartTwo,
 }
 
-#[derive(Debug)]
+// Serialized as e.g. {"outcome": "wrong_level"}
+#[derive(Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "outcome")]
 pub enum SubmissionOutcome {
     Correct,
     Incorrect,
@@ -44,17 +57,141 @@ pub enum SubmissionOutcome {
     WrongLevel,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum LeaderboardTiebreak {
+    // Members with the same score are sorted by ID
+    Id,
+    // Members with the same score are sorted by who got there first
+    Time,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum MarkdownFlavor {
+    // Plain CommonMark, with tables kept as code blocks and strikethrough as
+    // <del> tags
+    CommonMark,
+    // GitHub-Flavored Markdown, with pipe tables and ~~strikethrough~~
+    Gfm,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FileLayout {
+    // e.g. dir/input
+    Flat,
+    // e.g. dir/05/input
+    PerDay,
+    // e.g. dir/2023/05/input
+    YearDay,
+}
+
+// Marks for days with both stars, just the first star and no stars
+#[derive(Clone, Debug)]
+pub struct StarGlyphs {
+    pub both: String,
+    pub first: String,
+    pub none: String,
+}
+
+// Day 25's second part needs all 49 other stars
+#[derive(Clone, Copy, Debug)]
+pub struct Day25Status {
+    pub complete: bool,
+    pub part_two_unlockable: bool,
+    pub stars_missing: u32,
+}
+
+// Everything about a day that frontends usually show at once
+#[derive(Clone, Debug)]
+pub struct DayStatus {
+    pub unlocked: bool,
+    pub parts_unlocked: u8,
+    pub parts_solved: u8,
+    pub my_answers: (Option<String>, Option<String>),
+    pub unlock_time: DateTime<FixedOffset>,
+}
+
+// Where output goes, detected once so that colours, pagers and the width
+// of rendered text all agree on whether they're going to a terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputContext {
+    pub is_terminal: bool,
+    pub width: usize,
+    pub color: bool,
+}
+
+// An unlocked day still missing stars, from none to just the first one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingDay {
+    pub day: PuzzleDay,
+    pub stars: u8,
+}
+
+// A row of the global leaderboard, whose time is counted from the unlock
+#[derive(Clone, Debug)]
+pub struct GlobalLeaderboardEntry {
+    pub part: PuzzlePart,
+    pub rank: u32,
+    pub time: String,
+    pub name: String,
+}
+
+// The statement of an unlocked part and, once solved, your answer to it
+#[derive(Clone, Debug)]
+pub struct PuzzlePartContent {
+    pub part: PuzzlePart,
+    pub html: String,
+    pub markdown: String,
+    pub text: String,
+    pub solved: bool,
+    pub my_answer: Option<String>,
+}
+
+// Everything known about a puzzle, serialized by `export_json`
+#[derive(Debug, Serialize)]
+pub struct PuzzleExport {
+    pub schema_version: u32,
+    pub year: PuzzleYear,
+    pub day: PuzzleDay,
+    pub title: Option<String>,
+    pub html: String,
+    pub markdown: String,
+    pub text: String,
+    pub answers: Vec<String>,
+    pub solved_parts: Vec<PuzzlePart>,
+    pub unlocked_parts: Vec<PuzzlePart>,
+    pub input: String,
+}
+
+// Bump when fields of PuzzleExport are renamed, removed or change meaning
+const EXPORT_SCHEMA_VERSION: u32 = 1;
+
 const FIRST_EVENT_YEAR: PuzzleYear = 2015;
 const DECEMBER: u32 = 12;
 const FIRST_PUZZLE_DAY: PuzzleDay = 1;
 const LAST_PUZZLE_DAY: PuzzleDay = 25;
 const RELEASE_TIMEZONE_OFFSET: i32 = -5 * 3600;
 
+const AOC_URL: &str = "https://adventofcode.com";
+
 const SESSION_COOKIE_FILE: &str = "adventofcode.session";
 const HIDDEN_SESSION_COOKIE_FILE: &str = ".adventofcode.session";
 const SESSION_COOKIE_ENV_VAR: &str = "ADVENT_OF_CODE_SESSION";
+const SESSION_FILE_ENV_VAR: &str = "ADVENT_OF_CODE_SESSION_FILE";
 
 const DEFAULT_COL_WIDTH: usize = 80;
+const COLUMNS_ENV_VAR: &str = "COLUMNS";
+const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+const CLICOLOR_FORCE_ENV_VAR: &str = "CLICOLOR_FORCE";
+const DEFAULT_INPUT_FILENAME: &str = "input";
+const DEFAULT_PUZZLE_FILENAME: &str = "puzzle.md";
+const STDOUT_FILENAME: &str = "-";
+const MIRROR_PUZZLE_FILENAME: &str = "puzzle.html";
+
+const MAX_SNIPPET_LEN: usize = 200;
+const SUBMIT_RETRIES: u32 = 3;
+const SUBMIT_RETRY_DELAY: Duration = Duration::from_secs(2);
+const WAIT_RETRY_MARGIN: Duration = Duration::from_secs(2);
+const MAX_WAIT_RETRY_TIME: Duration = Duration::from_secs(15 * 60);
 
 const PKG_REPO: &str = env!("CARGO_PKG_REPOSITORY");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -87,6 +224,9 @@ pub enum AocError {
     #[error("Puzzle {0} of {1} is still locked")]
     LockedPuzzle(PuzzleDay, PuzzleYear),
 
+    #[error("Puzzle {0} of {1} unlocks more than a year from now")]
+    DistantPuzzle(PuzzleDay, PuzzleYear),
+
     #[error("Session cookie file not found in home or config directory")]
     SessionFileNotFound,
 
@@ -97,18 +237,53 @@ pub enum AocError {
         source: std::io::Error,
     },
 
+    #[error("Failed to run session cookie command '{command}': {source}")]
+    SessionCommandError {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("A session cookie is required for this request")]
+    SessionCookieRequired,
+
     #[error("Invalid session cookie")]
     InvalidSessionCookie,
 
+    #[error("Session cookie file '{0}' can be read by other users")]
+    InsecureSessionFile(String),
+
+    #[error("Not logged in, the session cookie may have expired")]
+    SessionExpired,
+
+    #[error("Invalid HTTP header '{0}'")]
+    InvalidHeader(String),
+
     #[error("HTTP request error: {0}")]
     HttpRequestError(#[from] reqwest::Error),
 
     #[error("Failed to parse Advent of Code response")]
     AocResponseError,
 
-    #[error("The private leaderboard does not exist or you are not a member")]
+    #[error("Advent of Code is unavailable, possibly for maintenance")]
+    ServiceUnavailable,
+
+    #[error("Unexpected page from Advent of Code: {0}")]
+    MissingMainElement(String),
+
+    #[error("You are not a member of the private leaderboard")]
     PrivateLeaderboardNotAvailable,
 
+    #[error("Private leaderboard {0} does not exist")]
+    PrivateLeaderboardNotFound(LeaderboardId),
+
+    #[error("Failed to read from file '{filename}': {source}")]
+    FileReadError {
+        filename: String,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("Failed to write to file '{filename}': {source}")]
     FileWriteError {
         filename: String,
@@ -122,12 +297,28 @@ pub enum AocError {
     #[error("Invalid puzzle part number")]
     InvalidPuzzlePart,
 
+    #[error("Part {0} of the puzzle is not unlocked yet")]
+    PuzzlePartLocked(PuzzlePart),
+
     #[error("Output width must be greater than zero")]
     InvalidOutputWidth,
+
+    #[error("Failed to run solution command '{command}': {source}")]
+    SolutionCommandError {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not find an answer in the solution output")]
+    AnswerNotFound,
+
+    #[error("Invalid answer on line {0}, expected \"day,part,answer\"")]
+    InvalidBatchLine(usize),
 }
 
 pub struct AocClient {
-    session_cookie: String,
+    session_cookie: Option<String>,
     unlock_datetime: DateTime<FixedOffset>,
     year: PuzzleYear,
     day: PuzzleDay,
@@ -136,6 +327,25 @@ pub struct AocClient {
     input_filename: PathBuf,
     puzzle_filename: PathBuf,
     show_html_markup: bool,
+    concise: bool,
+    leaderboard_tiebreak: LeaderboardTiebreak,
+    mirror_dir: Option<PathBuf>,
+    plain: bool,
+    markdown_width: Option<usize>,
+    star_glyphs: Option<StarGlyphs>,
+    emphasis: bool,
+    shared_cache_dir: Option<PathBuf>,
+    extra_headers: HeaderMap,
+    input_checksum: bool,
+    strip_answer_label: bool,
+    http_client: HttpClient,
+    session_cookie_source: Option<String>,
+    main_fallback: bool,
+    markdown_flavor: MarkdownFlavor,
+    compress_input: bool,
+    wait_retries: u32,
+    output_context: OutputContext,
+    timings: Mutex<Vec<(&'static str, Duration)>>,
 }
 
 #[must_use]
@@ -148,6 +358,29 @@ pub struct AocClientBuilder {
     input_filename: PathBuf,
     puzzle_filename: PathBuf,
     show_html_markup: bool,
+    concise: bool,
+    leaderboard_tiebreak: LeaderboardTiebreak,
+    anonymous: bool,
+    mirror_dir: Option<PathBuf>,
+    plain: bool,
+    markdown_width: Option<usize>,
+    reject_distant_puzzles: bool,
+    star_glyphs: Option<StarGlyphs>,
+    http2_prior_knowledge: bool,
+    pool_idle_timeout: Option<Duration>,
+    emphasis: bool,
+    shared_cache_dir: Option<PathBuf>,
+    extra_headers: HeaderMap,
+    input_checksum: bool,
+    strip_answer_label: bool,
+    http_client: Option<HttpClient>,
+    session_cookie_source: Option<String>,
+    main_fallback: bool,
+    strict_session_permissions: bool,
+    markdown_flavor: MarkdownFlavor,
+    compress_input: bool,
+    wait_retries: u32,
+    output_context: OutputContext,
 }
 
 impl AocClient {
@@ -163,6 +396,58 @@ impl AocClient {
             >= 0
     }
 
+    pub fn is_today(&self) -> bool {
+        // Puzzles unlock at midnight, so the unlock date is the puzzle's day
+        let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
+        let now = timezone.from_utc_datetime(&Utc::now().naive_utc());
+        self.unlock_datetime.date_naive() == now.date_naive()
+    }
+
+    pub fn days_behind(&self) -> Option<u32> {
+        last_unlocked_day(self.year)
+            .filter(|&last_day| last_day >= self.day)
+            .map(|last_day| last_day - self.day)
+    }
+
+    pub fn time_until_unlock(&self) -> Option<Duration> {
+        let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
+        let now = timezone.from_utc_datetime(&Utc::now().naive_utc());
+        self.unlock_datetime
+            .signed_duration_since(now)
+            .to_std()
+            .ok()
+            .filter(|duration| !duration.is_zero())
+    }
+
+    // Negative once the puzzle is unlocked, e.g. -5 five seconds after
+    pub fn seconds_until_unlock(&self) -> i64 {
+        let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
+        let now = timezone.from_utc_datetime(&Utc::now().naive_utc());
+        self.unlock_datetime
+            .signed_duration_since(now)
+            .num_seconds()
+    }
+
+    pub fn timings(&self) -> Vec<(&'static str, Duration)> {
+        self.timings.lock().unwrap().clone()
+    }
+
+    fn timed<T>(&self, operation: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.timings
+            .lock()
+            .unwrap()
+            .push((operation, start.elapsed()));
+        result
+    }
+
+    fn require_session_cookie(&self) -> AocResult<&str> {
+        self.session_cookie
+            .as_deref()
+            .ok_or(AocError::SessionCookieRequired)
+    }
+
     fn ensure_day_unlocked(&self) -> AocResult<()> {
         if self.day_unlocked() {
             Ok(())
@@ -171,49 +456,199 @@ impl AocClient {
         }
     }
 
+    pub fn year(&self) -> PuzzleYear {
+        self.year
+    }
+
+    pub fn day(&self) -> PuzzleDay {
+        self.day
+    }
+
+    // Where the session cookie came from, e.g. "file '/home/alice/...'"
+    pub fn session_cookie_source(&self) -> Option<&str> {
+        self.session_cookie_source.as_deref()
+    }
+
+    pub fn output_width(&self) -> usize {
+        self.output_width
+    }
+
+    pub fn output_context(&self) -> OutputContext {
+        self.output_context
+    }
+
+    pub fn input_filename(&self) -> &Path {
+        &self.input_filename
+    }
+
+    pub fn puzzle_filename(&self) -> &Path {
+        &self.puzzle_filename
+    }
+
+    // Where save_input really writes the input, which has a .gz suffix when
+    // it's compressed
+    pub fn saved_input_filename(&self) -> PathBuf {
+        self.saved_input_path(&self.input_filename)
+    }
+
+    pub fn saved_input_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        saved_input_path(path.as_ref(), self.compress_input)
+    }
+
+    pub fn puzzle_url(&self) -> String {
+        format!("{AOC_URL}/{}/day/{}", self.year, self.day)
+    }
+
+    pub fn input_url(&self) -> String {
+        format!("{}/input", self.puzzle_url())
+    }
+
+    pub fn answer_url(&self) -> String {
+        format!("{}/answer", self.puzzle_url())
+    }
+
+    fn mirror_file(
+        &self,
+        puzzle_dir: &Path,
+        filename: &str,
+    ) -> Option<PathBuf> {
+        self.mirror_dir
+            .as_ref()
+            .map(|dir| dir.join(puzzle_dir).join(filename))
+    }
+
+    // The shared cache is read-only and populated by someone else, e.g. a
+    // lab admin, so only what's the same for every account is read from it:
+    // the first part of puzzles. Inputs, answers, second parts and the stars
+    // on calendars always come from the website.
+    fn read_shared_cache(&self, path: &Path) -> Option<String> {
+        let file = self.shared_cache_dir.as_ref()?.join(path);
+        let contents = read_to_string(&file).ok()?;
+        debug!("🦌 Read '{}' from shared cache", file.display());
+        Some(contents)
+    }
+
     pub fn get_puzzle_html(&self) -> AocResult<String> {
-        self.ensure_day_unlocked()?;
+        self.get_puzzle_html_for(self.year, self.day)
+    }
+
+    // The *_for methods fetch another puzzle than the one the client was
+    // built for, with the same settings, e.g. when going through many days
+    pub fn get_puzzle_html_for(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+    ) -> AocResult<String> {
+        self.puzzle_html_for(year, day, true)
+    }
 
-        debug!("🦌 Fetching puzzle for day {}, {}", self.day, self.year);
+    // The whole puzzle page as seen by this account, with its answers and
+    // the second part once it's unlocked
+    fn own_puzzle_html(&self) -> AocResult<String> {
+        self.puzzle_html_for(self.year, self.day, false)
+    }
 
-        let url =
-            format!("https://adventofcode.com/{}/day/{}", self.year, self.day);
-        let response = http_client(&self.session_cookie, "text/html")?
-            .get(url)
-            .send()
-            .and_then(|response| response.error_for_status())
-            .and_then(|response| response.text())?;
-        let puzzle_html = Regex::new(r"(?i)(?s)<main>(?P<main>.*)</main>")
-            .unwrap()
-            .captures(&response)
-            .ok_or(AocError::AocResponseError)?
-            .name("main")
-            .unwrap()
-            .as_str()
-            .to_string();
+    fn puzzle_html_for(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+        shared_cache: bool,
+    ) -> AocResult<String> {
+        let mirror_dir = FileLayout::YearDay.directory("", year, day);
+        if let Some(file) =
+            self.mirror_file(&mirror_dir, MIRROR_PUZZLE_FILENAME)
+        {
+            debug!("🦌 Reading puzzle from '{}'", file.display());
+            return read_file(file);
+        }
+
+        let cached_file = mirror_dir.join(MIRROR_PUZZLE_FILENAME);
+        if let Some(part_one) = shared_cache
+            .then(|| self.read_shared_cache(&cached_file))
+            .flatten()
+            .and_then(|html| {
+                puzzle_articles(&html).first().map(|a| a.to_string())
+            })
+        {
+            return Ok(part_one);
+        }
+
+        ensure_unlocked(year, day)?;
 
-        Ok(puzzle_html)
+        debug!("🦌 Fetching puzzle for day {day}, {year}");
+
+        let response = self.timed("fetch puzzle", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    format!("{AOC_URL}/{year}/day/{day}"),
+                    self.session_cookie.as_deref(),
+                    "text/html",
+                )?
+                .send()?;
+            check_status(response)?.text().map_err(AocError::from)
+        })?;
+        self.main_element(&response)
     }
 
     pub fn get_input(&self) -> AocResult<String> {
-        self.ensure_day_unlocked()?;
+        self.get_input_for(self.year, self.day)
+    }
+
+    pub fn get_input_for(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+    ) -> AocResult<String> {
+        let mirror_dir = FileLayout::YearDay.directory("", year, day);
+        if let Some(file) =
+            self.mirror_file(&mirror_dir, DEFAULT_INPUT_FILENAME)
+        {
+            debug!("🦌 Reading input from '{}'", file.display());
+            return read_input_file(&file);
+        }
 
-        debug!("🦌 Fetching input for day {}, {}", self.day, self.year);
+        ensure_unlocked(year, day)?;
 
-        let url = format!(
-            "https://adventofcode.com/{}/day/{}/input",
-            self.year, self.day
-        );
-        http_client(&self.session_cookie, "text/plain")?
-            .get(url)
-            .send()
-            .and_then(|response| response.error_for_status())
-            .and_then(|response| response.text())
-            .map_err(AocError::from)
+        debug!("🦌 Fetching input for day {day}, {year}");
+
+        self.timed("fetch input", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    format!("{AOC_URL}/{year}/day/{day}/input"),
+                    Some(self.require_session_cookie()?),
+                    "text/plain",
+                )?
+                .send()?;
+            check_status(response)?.text().map_err(AocError::from)
+        })
+    }
+
+    // Any page of the website, e.g. "/2023/leaderboard/private", for pages
+    // this crate doesn't cover yet. The website asks that automated requests
+    // are kept to a minimum, so only fetch pages when needed and cache them.
+    pub fn get_page(&self, path: &str) -> AocResult<String> {
+        let url = format!("{AOC_URL}/{}", path.trim_start_matches('/'));
+        debug!("🦌 Fetching {url}");
+
+        self.timed("fetch page", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    url,
+                    self.session_cookie.as_deref(),
+                    "text/html",
+                )?
+                .send()?;
+            check_status(response)?.text().map_err(AocError::from)
+        })
     }
 
     fn submit_answer_html<P, D>(
         &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
         puzzle_part: P,
         answer: D,
     ) -> AocResult<String>
@@ -222,37 +657,107 @@ impl AocClient {
         AocError: From<P::Error>,
         D: Display,
     {
-        self.ensure_day_unlocked()?;
+        ensure_unlocked(year, day)?;
         let part: PuzzlePart = puzzle_part.try_into()?;
+        let answer = answer.to_string();
+        let answer = normalize_answer(&answer, self.strip_answer_label);
+
+        let mut retries = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            let outcome = self.post_answer(year, day, part, answer)?;
+            let wait = wait_time(&outcome);
+            let wait =
+                match wait_retry(wait, retries, self.wait_retries, waited) {
+                    WaitRetry::After(wait) => wait,
+                    WaitRetry::TooLong(wait) => {
+                        warn!(
+                            "⏳ Not waiting {}s more to resubmit, giving up",
+                            wait.as_secs()
+                        );
+                        return Ok(outcome);
+                    }
+                    WaitRetry::Stop => return Ok(outcome),
+                };
 
-        debug!(
-            "🦌 Submitting answer for part {part}, day {}, {}",
-            self.day, self.year
-        );
+            info!(
+                "⏳ Submitted too soon, resubmitting in {}s \
+                (Ctrl-C to cancel)",
+                wait.as_secs()
+            );
+            thread::sleep(wait);
+            waited += wait;
+            retries += 1;
+        }
+    }
+
+    fn post_answer(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+        part: PuzzlePart,
+        answer: &str,
+    ) -> AocResult<String> {
+        debug!("🦌 Submitting answer for part {part}, day {day}, {year}");
 
-        let url = format!(
-            "https://adventofcode.com/{}/day/{}/answer",
-            self.year, self.day
-        );
         let content_type = "application/x-www-form-urlencoded";
-        let response = http_client(&self.session_cookie, content_type)?
-            .post(url)
-            .body(format!("level={part}&answer={answer}"))
-            .send()
-            .and_then(|response| response.error_for_status())
-            .and_then(|response| response.text())
-            .map_err(AocError::HttpRequestError)?;
-
-        let outcome_html = Regex::new(r"(?i)(?s)<main>(?P<main>.*)</main>")
-            .unwrap()
-            .captures(&response)
-            .ok_or(AocError::AocResponseError)?
-            .name("main")
-            .unwrap()
-            .as_str()
-            .to_string();
+        let response = self.timed("submit answer", || {
+            let session_cookie = self.require_session_cookie()?;
+
+            let mut attempt = 1;
+            loop {
+                let result = self
+                    .request(
+                        Method::POST,
+                        format!("{AOC_URL}/{year}/day/{day}/answer"),
+                        Some(session_cookie),
+                        content_type,
+                    )?
+                    .body(format!("level={part}&answer={answer}"))
+                    .send();
+
+                let retry = match result {
+                    Err(err) => {
+                        let failure = if err.is_connect() {
+                            SubmitFailure::Connect
+                        } else {
+                            SubmitFailure::Request
+                        };
+                        if !may_resubmit(failure) {
+                            return Err(AocError::HttpRequestError(err));
+                        }
+                        err
+                    }
+                    Ok(response) => {
+                        let status = response.status();
+                        let status_error =
+                            response.error_for_status_ref().err();
+                        let body = response.text()?;
+                        let Some(err) = status_error else {
+                            return Ok(body);
+                        };
+                        if !may_resubmit(SubmitFailure::Status(status, &body)) {
+                            return Err(AocError::HttpRequestError(err));
+                        }
+                        err
+                    }
+                };
 
-        Ok(outcome_html)
+                if attempt > SUBMIT_RETRIES {
+                    return Err(AocError::HttpRequestError(retry));
+                }
+
+                let delay = SUBMIT_RETRY_DELAY * attempt;
+                warn!(
+                    "🦌 Failed to submit answer ({retry}), retrying in {}s",
+                    delay.as_secs()
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+        })?;
+
+        self.main_element(&response)
     }
 
     pub fn submit_answer<P, D>(
@@ -265,7 +770,23 @@ impl AocClient {
         AocError: From<P::Error>,
         D: Display,
     {
-        let outcome = self.submit_answer_html(puzzle_part, answer)?;
+        self.submit_answer_for(self.year, self.day, puzzle_part, answer)
+    }
+
+    pub fn submit_answer_for<P, D>(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+        puzzle_part: P,
+        answer: D,
+    ) -> AocResult<SubmissionOutcome>
+    where
+        P: TryInto<PuzzlePart>,
+        AocError: From<P::Error>,
+        D: Display,
+    {
+        let outcome =
+            self.submit_answer_html(year, day, puzzle_part, answer)?;
         if outcome.contains("That's the right answer") {
             Ok(SubmissionOutcome::Correct)
         } else if outcome.contains("That's not the right answer") {
@@ -291,503 +812,2193 @@ impl AocClient {
         AocError: From<P::Error>,
         D: Display,
     {
-        let outcome_html = self.submit_answer_html(puzzle_part, answer)?;
+        let outcome_html =
+            self.submit_answer_html(self.year, self.day, puzzle_part, answer)?;
         println!("\n{}", self.html2text(&outcome_html));
         Ok(())
     }
 
-    pub fn show_puzzle(&self) -> AocResult<()> {
-        let puzzle_html = self.get_puzzle_html()?;
-        println!("\n{}", self.html2text(&puzzle_html));
-        Ok(())
+    pub fn get_puzzle_title(&self) -> AocResult<Option<String>> {
+        Ok(puzzle_title(&self.get_puzzle_html()?))
     }
 
-    pub fn save_puzzle_markdown(&self) -> AocResult<()> {
-        let puzzle_html = self.get_puzzle_html()?;
-        let puzzle_markdow = parse_html(&puzzle_html);
-        save_file(
-            &self.puzzle_filename,
-            self.overwrite_files,
-            &puzzle_markdow,
-        )?;
-        info!("🎅 Saved puzzle to '{}'", self.puzzle_filename.display());
-        Ok(())
+    // e.g. "day05-scratchcards", for files named after the puzzle
+    pub fn get_title_filename_stem(&self) -> AocResult<String> {
+        let day = format!("day{:02}", self.day);
+        Ok(match self.get_puzzle_title()?.as_deref().map(slugify) {
+            Some(slug) if !slug.is_empty() => format!("{day}-{slug}"),
+            _ => day,
+        })
     }
 
-    pub fn save_input(&self) -> AocResult<()> {
-        let input = self.get_input()?;
-        save_file(&self.input_filename, self.overwrite_files, &input)?;
-        info!("🎅 Saved input to '{}'", self.input_filename.display());
-        Ok(())
+    pub fn get_my_answers(&self) -> AocResult<Vec<String>> {
+        Ok(puzzle_answers(&self.own_puzzle_html()?))
     }
 
-    pub fn get_calendar_html(&self) -> AocResult<String> {
-        debug!("🦌 Fetching {} calendar", self.year);
+    // The try_get_* methods return None for puzzles that are still locked,
+    // which helps when going through days optimistically
+    pub fn try_get_puzzle_html(&self) -> AocResult<Option<String>> {
+        none_if_locked(self.get_puzzle_html())
+    }
+
+    pub fn try_get_puzzle_text(&self) -> AocResult<Option<String>> {
+        none_if_locked(self.get_puzzle_text())
+    }
+
+    pub fn try_get_input(&self) -> AocResult<Option<String>> {
+        none_if_locked(self.get_input())
+    }
 
-        let url = format!("https://adventofcode.com/{}", self.year);
-        let response = http_client(&self.session_cookie, "text/html")?
-            .get(url)
-            .send()?;
+    pub fn solved_parts(&self) -> AocResult<Vec<PuzzlePart>> {
+        let answers = self.get_my_answers()?;
+        Ok(first_parts(answers.len()))
+    }
 
-        if response.status() == StatusCode::NOT_FOUND {
-            // A 402 reponse means the calendar for
-            // the requested year is not yet available
-            return Err(AocError::InvalidEventYear(self.year));
+    // Needs a single fetch of the puzzle, and none before it unlocks
+    pub fn day_status(&self) -> AocResult<DayStatus> {
+        let mut status = DayStatus {
+            unlocked: self.day_unlocked(),
+            parts_unlocked: 0,
+            parts_solved: 0,
+            my_answers: (None, None),
+            unlock_time: self.unlock_datetime,
+        };
+        if !status.unlocked {
+            return Ok(status);
         }
 
-        let contents = response.error_for_status()?.text()?;
+        let puzzle_html = self.own_puzzle_html()?;
+        let answers = puzzle_answers(&puzzle_html);
+        status.parts_solved = answers.len() as u8;
+        let mut answers = answers.into_iter();
+        status.my_answers = (answers.next(), answers.next());
+        status.parts_unlocked = puzzle_articles(&puzzle_html).len() as u8;
+        Ok(status)
+    }
 
-        if Regex::new(r#"href="/[0-9]{4}/auth/login""#)
-            .unwrap()
-            .is_match(&contents)
-        {
-            warn!(
-                "🍪 It looks like you are not logged in, try logging in again"
-            );
+    pub fn day25_status(&self) -> AocResult<Option<Day25Status>> {
+        if self.day != LAST_PUZZLE_DAY {
+            return Ok(None);
         }
 
-        let main = Regex::new(r"(?i)(?s)<main>(?P<main>.*)</main>")
-            .unwrap()
-            .captures(&contents)
-            .ok_or(AocError::AocResponseError)?
-            .name("main")
-            .unwrap()
-            .as_str()
-            .to_string();
+        let puzzle_html = self.own_puzzle_html()?;
+        let solved = puzzle_answers(&puzzle_html).len();
+        let complete = solved == 2
+            || puzzle_html.contains("Both parts of this puzzle are complete!");
 
-        // Remove elements that won't render well in the terminal
-        let cleaned_up = Regex::new(concat!(
-            // Remove 2015 "calendar-bkg"
-            r#"(<div class="calendar-bkg">[[:space:]]*"#,
-            r#"(<div>[^<]*</div>[[:space:]]*)*</div>)"#,
-            // Remove 2017 "naughty/nice" animation
-            r#"|(<div class="calendar-printer">(?s:.)*"#,
-            r#"\|O\|</span></div>[[:space:]]*)"#,
-            // Remove 2018 "space mug"
-            r#"|(<pre id="spacemug"[^>]*>[^<]*</pre>)"#,
-            // Remove 2019 shadows
-            r#"|(<span style="color[^>]*position:absolute"#,
-            r#"[^>]*>\.</span>)"#,
-            // Remove 2019 "sunbeam"
-            r#"|(<span class="sunbeam"[^>]*>"#,
-            r#"<span style="animation-delay[^>]*>\*</span></span>)"#,
+        // e.g. "You still need <span>3</span> more stars"
+        let stars_missing = Regex::new(concat!(
+            r"(?i)you (?:still )?need (?:<[^>]*>)*(?P<stars>\d+)",
+            r"(?:<[^>]*>)* more stars?",
         ))
         .unwrap()
-        .replace_all(&main, "")
-        .to_string();
+        .captures(&puzzle_html)
+        .and_then(|captures| captures["stars"].parse().ok())
+        .filter(|_| !complete)
+        .unwrap_or(0);
+
+        Ok(Some(Day25Status {
+            complete,
+            part_two_unlockable: solved == 1 && stars_missing == 0,
+            stars_missing,
+        }))
+    }
 
-        let class_regex =
-            Regex::new(r#"<a [^>]*class="(?P<class>[^"]*)""#).unwrap();
-        let star_regex = Regex::new(concat!(
-            r#"(?P<stars><span class="calendar-mark-complete">\*</span>"#,
-            r#"<span class="calendar-mark-verycomplete">\*</span>)"#,
-        ))
-        .unwrap();
+    pub fn get_puzzle_export(&self) -> AocResult<PuzzleExport> {
+        let html = self.own_puzzle_html()?;
+        let input = self.get_input()?;
+        let answers = puzzle_answers(&html);
+
+        Ok(PuzzleExport {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            year: self.year,
+            day: self.day,
+            title: puzzle_title(&html),
+            markdown: self.puzzle_markdown(&html),
+            text: self.puzzle_text(&html),
+            solved_parts: first_parts(answers.len()),
+            unlocked_parts: first_parts(puzzle_articles(&html).len()),
+            answers,
+            html,
+            input,
+        })
+    }
 
-        let all_stars = main.contains("calendar calendar-perfect");
+    pub fn export_json(&self) -> AocResult<String> {
+        let export = self.get_puzzle_export()?;
+        serde_json::to_string_pretty(&export)
+            .map_err(|_| AocError::AocResponseError)
+    }
 
-        // Remove stars that have not been collected
-        let calendar = cleaned_up
-            .lines()
-            .map(|line| {
-                let class = class_regex
-                    .captures(line)
-                    .and_then(|c| c.name("class"))
-                    .map(|c| c.as_str())
-                    .unwrap_or("");
+    pub fn get_example_answers(
+        &self,
+    ) -> AocResult<Vec<(String, Option<String>)>> {
+        Ok(example_answers(&self.get_puzzle_html()?))
+    }
 
-                let stars =
-                    if class.contains("calendar-verycomplete") || all_stars {
-                        "**"
-                    } else if class.contains("calendar-complete") {
-                        "*"
-                    } else {
-                        ""
-                    };
+    pub fn get_puzzle_text(&self) -> AocResult<String> {
+        let puzzle_html = self.get_puzzle_html()?;
+        Ok(self.puzzle_text(&puzzle_html))
+    }
 
-                star_regex.replace(line, stars)
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+    // Renders a puzzle saved with e.g. `aoc read --format raw-html`
+    pub fn get_html_file_text<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> AocResult<String> {
+        let puzzle_html = read_file(path)?;
+        Ok(self.puzzle_text(&puzzle_html))
+    }
 
-        Ok(calendar)
+    fn puzzle_text(&self, puzzle_html: &str) -> String {
+        let mut puzzle_html = puzzle_html.to_string();
+        if self.concise {
+            // Drop everything after both parts have been completed, i.e. the
+            // "Both parts of this puzzle are complete!" message and links
+            puzzle_html = Regex::new(concat!(
+                r#"(?s)<p class="day-success">"#,
+                r#"Both parts of this puzzle are complete!.*"#,
+            ))
+            .unwrap()
+            .replace(&puzzle_html, "")
+            .to_string();
+        }
+        self.html2text(&puzzle_html)
     }
 
-    pub fn show_calendar(&self) -> AocResult<()> {
-        let calendar_html = self.get_calendar_html()?;
-        let calendar_text = from_read_with_decorator(
-            calendar_html.as_bytes(),
-            self.output_width,
-            TrivialDecorator::new(),
-        );
-        println!("\n{calendar_text}");
+    pub fn show_puzzle(&self) -> AocResult<()> {
+        let puzzle_text = self.get_puzzle_text()?;
+        println!("\n{puzzle_text}");
         Ok(())
     }
 
-    fn get_private_leaderboard(
-        &self,
-        leaderboard_id: LeaderboardId,
-    ) -> AocResult<PrivateLeaderboard> {
-        debug!("🦌 Fetching private leaderboard {leaderboard_id}");
+    pub fn get_puzzle_part_html<P>(&self, puzzle_part: P) -> AocResult<String>
+    where
+        P: TryInto<PuzzlePart>,
+        AocError: From<P::Error>,
+    {
+        let part: PuzzlePart = puzzle_part.try_into()?;
+        let index = match part {
+            PuzzlePart::PartOne => 0,
+            PuzzlePart::PartTwo => 1,
+        };
 
-        let url = format!(
-            "https://adventofcode.com/{}/leaderboard/private/view\
-            /{leaderboard_id}.json",
-            self.year,
-        );
-        let response = http_client(&self.session_cookie, "application/json")?
-            .get(url)
-            .send()
-            .and_then(|response| response.error_for_status())?;
+        let puzzle_html = match part {
+            PuzzlePart::PartOne => self.get_puzzle_html()?,
+            PuzzlePart::PartTwo => self.own_puzzle_html()?,
+        };
+        puzzle_articles(&puzzle_html)
+            .get(index)
+            .map(|article| article.to_string())
+            .ok_or(AocError::PuzzlePartLocked(part))
+    }
 
-        if response.status() == StatusCode::FOUND {
-            // A 302 reponse is a redirect and it means
-            // the leaderboard doesn't exist or we can't access it
-            return Err(AocError::PrivateLeaderboardNotAvailable);
-        }
+    // Every unlocked part from a single fetch of the puzzle
+    pub fn get_parts(&self) -> AocResult<Vec<PuzzlePartContent>> {
+        let puzzle_html = self.own_puzzle_html()?;
+        let mut answers = puzzle_answers(&puzzle_html).into_iter();
+        let articles = puzzle_articles(&puzzle_html);
+
+        let parts = first_parts(articles.len())
+            .into_iter()
+            .zip(articles)
+            .map(|(part, article)| {
+                let my_answer = answers.next();
+                PuzzlePartContent {
+                    part,
+                    html: article.to_string(),
+                    markdown: self.puzzle_markdown(article),
+                    text: self.html2text(article),
+                    solved: my_answer.is_some(),
+                    my_answer,
+                }
+            })
+            .collect();
 
-        response.json().map_err(AocError::from)
+        Ok(parts)
     }
 
-    pub fn show_private_leaderboard(
-        &self,
-        leaderboard_id: LeaderboardId,
-    ) -> AocResult<()> {
-        let last_unlocked_day = last_unlocked_day(self.year)
-            .ok_or(AocError::InvalidEventYear(self.year))?;
-        let leaderboard = self.get_private_leaderboard(leaderboard_id)?;
-        let owner_name = leaderboard
-            .get_owner_name()
-            .ok_or(AocError::AocResponseError)?;
+    pub fn get_puzzle_part_text<P>(&self, puzzle_part: P) -> AocResult<String>
+    where
+        P: TryInto<PuzzlePart>,
+        AocError: From<P::Error>,
+    {
+        let part_html = self.get_puzzle_part_html(puzzle_part)?;
+        Ok(self.html2text(&part_html))
+    }
 
-        println!(
-            "Private leaderboard of {} for Advent of Code {}.\n\n\
-            {} indicates the user got both stars for that day,\n\
-            {} means just the first star, and a {} means none.\n",
-            owner_name.bold(),
-            self.year.to_string().bold(),
-            "Gold *".color(GOLD),
-            "silver *".color(SILVER),
-            "gray dot (.)".color(DARK_GRAY),
-        );
+    pub fn show_puzzle_part<P>(&self, puzzle_part: P) -> AocResult<()>
+    where
+        P: TryInto<PuzzlePart>,
+        AocError: From<P::Error>,
+    {
+        let part_text = self.get_puzzle_part_text(puzzle_part)?;
+        println!("\n{part_text}");
+        Ok(())
+    }
 
-        let mut members: Vec<_> = leaderboard.members.values().collect();
-        members.sort_by_key(|member| Reverse(*member));
+    pub fn save_puzzle_markdown(&self) -> AocResult<()> {
+        self.save_puzzle_markdown_for(
+            self.year,
+            self.day,
+            &self.puzzle_filename,
+        )
+    }
 
-        let highest_score = members.first().map(|m| m.local_score).unwrap_or(0);
-        let score_width = highest_score.to_string().len();
-        let highest_rank = 1 + leaderboard.members.len();
-        let rank_width = highest_rank.to_string().len();
-        let header_pad: String =
-            vec![' '; rank_width + score_width].into_iter().collect();
+    pub fn save_puzzle_markdown_for<P: AsRef<Path>>(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+        path: P,
+    ) -> AocResult<()> {
+        let path = path.as_ref();
+        let puzzle_html = self.get_puzzle_html_for(year, day)?;
+        let puzzle_markdow = self.puzzle_markdown(&puzzle_html);
+        save_file(path, self.overwrite_files, &puzzle_markdow)?;
+        if !is_stdout(path) {
+            info!("🎅 Saved puzzle to '{}'", path.display());
+        }
+        Ok(())
+    }
 
-        for header in ["         1111111111222222", "1234567890123456789012345"]
-        {
-            let (on, off) = header.split_at(last_unlocked_day as usize);
-            println!("{header_pad}   {}{}", on, off.color(DARK_GRAY));
+    fn puzzle_markdown(&self, puzzle_html: &str) -> String {
+        let mut puzzle_markdown =
+            self.timed("convert to markdown", || html_to_markdown(puzzle_html));
+        if let MarkdownFlavor::CommonMark = self.markdown_flavor {
+            puzzle_markdown = gfm_to_commonmark(&puzzle_markdown);
+        }
+        match self.markdown_width {
+            Some(width) => wrap_markdown(&puzzle_markdown, width),
+            None => puzzle_markdown,
         }
+    }
 
-        for (member, rank) in members.iter().zip(1..) {
-            let stars: String = (FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY)
-                .map(|day| {
-                    if day > last_unlocked_day {
-                        " ".normal()
-                    } else {
-                        match member.count_stars(day) {
-                            2 => "*".color(GOLD),
-                            1 => "*".color(SILVER),
-                            _ => ".".color(DARK_GRAY),
-                        }
-                    }
-                    .to_string()
-                })
-                .collect();
+    pub fn save_input(&self) -> AocResult<()> {
+        self.save_input_for(self.year, self.day, &self.input_filename)
+    }
 
-            println!(
-                "{rank:rank_width$}) {:score_width$} {stars}  {}",
-                member.local_score,
-                member.get_name(),
-            );
+    pub fn save_input_for<P: AsRef<Path>>(
+        &self,
+        year: PuzzleYear,
+        day: PuzzleDay,
+        path: P,
+    ) -> AocResult<()> {
+        let input = self.get_input_for(year, day)?;
+        if is_stdout(path.as_ref()) {
+            return save_file(path, self.overwrite_files, input);
+        }
+
+        let filename = self.saved_input_path(path);
+        let contents = if self.compress_input {
+            gzip(&input).map_err(|err| AocError::FileWriteError {
+                filename: filename.display().to_string(),
+                source: err,
+            })?
+        } else {
+            input.into_bytes()
+        };
+        save_file_atomically(&filename, self.overwrite_files, &contents)?;
+        info!("🎅 Saved input to '{}'", filename.display());
+
+        if self.input_checksum {
+            // Same format as the output of sha256sum
+            let mut checksum_filename = filename.clone();
+            checksum_filename.as_mut_os_string().push(".sha256");
+            let input_name =
+                filename.file_name().unwrap_or_default().to_string_lossy();
+            let checksum =
+                format!("{:x}  {input_name}\n", Sha256::digest(&contents));
+            save_file_atomically(
+                &checksum_filename,
+                self.overwrite_files,
+                &checksum,
+            )?;
+            info!("🎅 Saved checksum to '{}'", checksum_filename.display());
         }
 
         Ok(())
     }
 
-    fn html2text(&self, html: &str) -> String {
-        if self.show_html_markup {
-            from_read(html.as_bytes(), self.output_width)
-        } else {
-            from_read_with_decorator(
-                html.as_bytes(),
-                self.output_width,
-                TrivialDecorator::new(),
-            )
+    // One self-contained markdown document with the puzzle, its examples
+    // and the input, written in full or not at all
+    pub fn save_bundle<P: AsRef<Path>>(&self, path: P) -> AocResult<()> {
+        let puzzle_html = self.get_puzzle_html()?;
+        let mut bundle = self.puzzle_markdown(&puzzle_html);
+
+        let examples = example_answers(&puzzle_html);
+        if !examples.is_empty() {
+            bundle.push_str("\n\n## Examples\n");
+        }
+        for ((example, answer), number) in examples.iter().zip(1..) {
+            let example = example.trim_end_matches('\n');
+            bundle.push_str(&format!("\n### Example {number}\n\n"));
+            bundle.push_str(&format!("```\n{example}\n```\n"));
+            if let Some(answer) = answer {
+                bundle.push_str(&format!("\nAnswer: `{answer}`\n"));
+            }
         }
-    }
-}
 
-impl Default for AocClientBuilder {
-    fn default() -> Self {
-        let session_cookie = None;
-        let year = None;
-        let day = None;
-        let output_width = term_size::dimensions()
-            .map(|(w, _)| w)
-            .unwrap_or(DEFAULT_COL_WIDTH);
-        let overwrite_files = false;
-        let input_filename = "input".into();
-        let puzzle_filename = "puzzle.md".into();
-        let show_html_markup = false;
+        let input = self.get_input()?;
+        let input = input.trim_end_matches('\n');
+        bundle.push_str(&format!("\n## Input\n\n```\n{input}\n```\n"));
 
-        Self {
-            session_cookie,
-            year,
-            day,
-            output_width,
-            overwrite_files,
-            input_filename,
-            puzzle_filename,
-            show_html_markup,
+        save_file_atomically(&path, self.overwrite_files, &bundle)?;
+        if !is_stdout(path.as_ref()) {
+            info!("🎅 Saved bundle to '{}'", path.as_ref().display());
         }
+        Ok(())
     }
-}
 
-impl AocClientBuilder {
-    pub fn build(&self) -> AocResult<AocClient> {
-        for (missing, field) in [
-            (self.session_cookie.is_none(), "session cookie"),
-            (self.year.is_none(), "year"),
-            (self.day.is_none(), "day"),
-        ] {
-            if missing {
-                return Err(AocError::ClientFieldMissing(field.to_string()));
-            }
-        }
+    pub fn run_solution(&self, command: &str) -> AocResult<String> {
+        let input = self.get_input()?;
 
-        let day = self.day.unwrap();
-        let year = self.year.unwrap();
-        let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
-        let local_datetime = NaiveDate::from_ymd_opt(year, DECEMBER, day)
-            .ok_or(AocError::InvalidPuzzleDate(day, year))?
-            .and_hms_opt(0, 0, 0)
-            .unwrap();
-        let unlock_datetime = timezone
-            .from_local_datetime(&local_datetime)
-            .single()
-            .ok_or(AocError::InvalidPuzzleDate(day, year))?;
+        debug!("🦌 Running solution command '{command}'");
 
-        Ok(AocClient {
-            session_cookie: self.session_cookie.clone().unwrap(),
-            unlock_datetime,
-            year: self.year.unwrap(),
-            day: self.day.unwrap(),
-            output_width: self.output_width,
-            overwrite_files: self.overwrite_files,
-            input_filename: self.input_filename.clone(),
-            puzzle_filename: self.puzzle_filename.clone(),
-            show_html_markup: self.show_html_markup,
-        })
+        self.timed("run solution", || run_command(command, Some(input)))
+            .map_err(|err| AocError::SolutionCommandError {
+                command: command.to_string(),
+                source: err,
+            })
     }
 
-    pub fn session_cookie(
-        &mut self,
-        session_cookie: impl AsRef<str>,
-    ) -> AocResult<&mut Self> {
-        let cookie = session_cookie.as_ref().trim();
-        if cookie.is_empty() || !cookie.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(AocError::InvalidSessionCookie);
+    fn get_calendar_main(&self) -> AocResult<String> {
+        let contents = self.fetch_calendar()?;
+        self.main_element(&contents)
+    }
+
+    // Pages without a <main> element are usually error pages, so their body
+    // is either used instead or quoted in the error
+    fn main_element(&self, html: &str) -> AocResult<String> {
+        let main_regex = Regex::new(r"(?i)(?s)<main>(?P<main>.*)</main>");
+        if let Some(captures) = main_regex.unwrap().captures(html) {
+            return Ok(captures["main"].to_string());
         }
-        self.session_cookie = Some(cookie.to_string());
-        Ok(self)
+
+        let body = Regex::new(r"(?i)(?s)<body[^>]*>(?P<body>.*)</body>")
+            .unwrap()
+            .captures(html)
+            .map_or(html, |captures| captures.name("body").unwrap().as_str());
+        if is_maintenance_page(html) {
+            return Err(AocError::ServiceUnavailable);
+        }
+        if self.main_fallback {
+            warn!("🦌 Response has no <main> element, using the whole page");
+            return Ok(body.to_string());
+        }
+
+        let text = unescape_html(body);
+        let mut snippet = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some((end, _)) = snippet.char_indices().nth(MAX_SNIPPET_LEN) {
+            snippet.truncate(end);
+            snippet.push_str("...");
+        }
+        Err(AocError::MissingMainElement(snippet))
     }
 
-    pub fn session_cookie_from_default_locations(
-        &mut self,
-    ) -> AocResult<&mut Self> {
-        if let Ok(cookie) = env::var(SESSION_COOKIE_ENV_VAR) {
-            if !cookie.trim().is_empty() {
-                debug!(
-                    "🍪 Loading session cookie from '{SESSION_COOKIE_ENV_VAR}' \
-                    environment variable"
-                );
+    fn fetch_calendar(&self) -> AocResult<String> {
+        debug!("🦌 Fetching {} calendar", self.year);
 
-                return self.session_cookie(&cookie);
+        let url = format!("{AOC_URL}/{}", self.year);
+        let contents = self.timed("fetch calendar", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    url,
+                    self.session_cookie.as_deref(),
+                    "text/html",
+                )?
+                .send()?;
+
+            if response.status() == StatusCode::NOT_FOUND {
+                // A 402 reponse means the calendar for
+                // the requested year is not yet available
+                return Err(AocError::InvalidEventYear(self.year));
             }
 
+            check_status(response)?.text().map_err(AocError::from)
+        })?;
+
+        if Regex::new(r#"href="/[0-9]{4}/auth/login""#)
+            .unwrap()
+            .is_match(&contents)
+        {
             warn!(
-                "🍪 Environment variable '{SESSION_COOKIE_ENV_VAR}' is set \
-                but it is empty, ignoring"
+                "🍪 It looks like you are not logged in, try logging in again"
             );
         }
 
-        let path = if let Some(home_path) = home_dir()
-            .map(|dir| dir.join(HIDDEN_SESSION_COOKIE_FILE))
-            .filter(|file| file.exists())
-        {
-            home_path
-        } else if let Some(config_path) = config_dir()
-            .map(|dir| dir.join(SESSION_COOKIE_FILE))
-            .filter(|file| file.exists())
-        {
-            config_path
-        } else {
-            return Err(AocError::SessionFileNotFound);
-        };
+        Ok(contents)
+    }
 
-        self.session_cookie_from_file(path)
+    pub fn get_calendar_html(&self) -> AocResult<String> {
+        let main = self.get_calendar_main()?;
+
+        // Remove elements that won't render well in the terminal
+        let cleaned_up = Regex::new(concat!(
+            // Remove 2015 "calendar-bkg"
+            r#"(<div class="calendar-bkg">[[:space:]]*"#,
+            r#"(<div>[^<]*</div>[[:space:]]*)*</div>)"#,
+            // Remove 2017 "naughty/nice" animation
+            r#"|(<div class="calendar-printer">(?s:.)*"#,
+            r#"\|O\|</span></div>[[:space:]]*)"#,
+            // Remove 2018 "space mug"
+            r#"|(<pre id="spacemug"[^>]*>[^<]*</pre>)"#,
+            // Remove 2019 shadows
+            r#"|(<span style="color[^>]*position:absolute"#,
+            r#"[^>]*>\.</span>)"#,
+            // Remove 2019 "sunbeam"
+            r#"|(<span class="sunbeam"[^>]*>"#,
+            r#"<span style="animation-delay[^>]*>\*</span></span>)"#,
+        ))
+        .unwrap()
+        .replace_all(&main, "")
+        .to_string();
+
+        let class_regex =
+            Regex::new(r#"<a [^>]*class="(?P<class>[^"]*)""#).unwrap();
+        let star_regex = Regex::new(concat!(
+            r#"(?P<stars><span class="calendar-mark-complete">\*</span>"#,
+            r#"<span class="calendar-mark-verycomplete">\*</span>)"#,
+        ))
+        .unwrap();
+
+        let all_stars = main.contains("calendar calendar-perfect");
+
+        // Remove stars that have not been collected
+        let calendar = cleaned_up
+            .lines()
+            .map(|line| {
+                let class = class_regex
+                    .captures(line)
+                    .and_then(|c| c.name("class"))
+                    .map(|c| c.as_str())
+                    .unwrap_or("");
+
+                // Without glyphs of their own, days show the stars they got
+                let stars = count_calendar_stars(class, all_stars);
+                let stars = match (&self.star_glyphs, stars) {
+                    (Some(glyphs), 2) => glyphs.both.as_str(),
+                    (Some(glyphs), 1) => glyphs.first.as_str(),
+                    (Some(glyphs), _) => glyphs.none.as_str(),
+                    (None, 2) => "**",
+                    (None, 1) => "*",
+                    (None, _) => "",
+                };
+
+                star_regex.replace(line, stars)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(calendar)
     }
 
-    pub fn session_cookie_from_file<P: AsRef<Path>>(
+    pub fn get_star_map(&self) -> AocResult<BTreeMap<PuzzleDay, u8>> {
+        let main = self.get_calendar_main()?;
+        let all_stars = main.contains("calendar calendar-perfect");
+        let day_regex = Regex::new(concat!(
+            r#"<a [^>]*class="(?P<class>calendar-day(?P<day>[0-9]+)"#,
+            r#"[^"]*)""#,
+        ))
+        .unwrap();
+
+        // Only unlocked days are links in the calendar
+        let star_map = day_regex
+            .captures_iter(&main)
+            .filter_map(|captures| {
+                let day = captures["day"].parse().ok()?;
+                let stars = count_calendar_stars(&captures["class"], all_stars);
+                Some((day, stars))
+            })
+            .collect();
+
+        Ok(star_map)
+    }
+
+    // The name shown at the top of the page, which is an "(anonymous user
+    // #1234)" label for users that didn't link a name to their account
+    pub fn get_username(&self) -> AocResult<String> {
+        let user = self.get_user_header()?;
+        let name = Regex::new(r"^(?P<name>[^<]*)")
+            .unwrap()
+            .captures(&user)
+            .map(|captures| unescape_html(captures["name"].trim()))
+            .unwrap_or_default();
+
+        if name.is_empty() {
+            return Err(AocError::AocResponseError);
+        }
+        Ok(name)
+    }
+
+    // Supporters have an "(AoC++)" badge next to their name
+    pub fn is_supporter(&self) -> AocResult<bool> {
+        let user = self.get_user_header()?;
+        Ok(user.contains(r#"class="supporter-badge""#))
+    }
+
+    fn get_user_header(&self) -> AocResult<String> {
+        let contents = self.fetch_calendar()?;
+        Regex::new(r#"(?s)<div class="user">(?P<user>.*?)</div>"#)
+            .unwrap()
+            .captures(&contents)
+            .map(|captures| captures["user"].to_string())
+            .ok_or(AocError::SessionExpired)
+    }
+
+    // A 5x5 grid of days coloured by stars collected, for sharing progress
+    pub fn get_calendar_svg(&self) -> AocResult<String> {
+        let star_map = self.get_star_map()?;
+        let (cell, gap, header) = (60, 6, 40);
+        let side = 5 * cell + 6 * gap;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{side}\" \
+            height=\"{}\" font-family=\"monospace\">\n\
+            <rect width=\"100%\" height=\"100%\" fill=\"#0f0f23\"/>\n\
+            <text x=\"{}\" y=\"28\" fill=\"#00cc00\" font-size=\"20\" \
+            text-anchor=\"middle\">Advent of Code {}</text>\n",
+            side + header,
+            side / 2,
+            self.year
+        );
+        for day in FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY {
+            let (row, col) = ((day - 1) / 5, (day - 1) % 5);
+            let x = gap + col * (cell + gap);
+            let y = header + gap + row * (cell + gap);
+            let (fill, text) = match star_map.get(&day) {
+                Some(2) => ("#ffff66", "#0f0f23"),
+                Some(1) => ("#9999cc", "#0f0f23"),
+                Some(_) => ("#333340", "#cccccc"),
+                // Days that are still locked
+                None => ("#1a1a2e", "#666666"),
+            };
+            svg.push_str(&format!(
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{cell}\" \
+                height=\"{cell}\" rx=\"6\" fill=\"{fill}\"/>\n\
+                <text x=\"{}\" y=\"{}\" fill=\"{text}\" font-size=\"22\" \
+                text-anchor=\"middle\">{day}</text>\n",
+                x + cell / 2,
+                y + cell / 2 + 8,
+            ));
+        }
+        svg.push_str("</svg>\n");
+
+        Ok(svg)
+    }
+
+    pub fn save_calendar_svg<P: AsRef<Path>>(&self, path: P) -> AocResult<()> {
+        let svg = self.get_calendar_svg()?;
+        save_file(&path, self.overwrite_files, &svg)?;
+        if !is_stdout(path.as_ref()) {
+            info!("🎅 Saved calendar to '{}'", path.as_ref().display());
+        }
+        Ok(())
+    }
+
+    // Unlocked days with fewer than two stars, in ascending order
+    pub fn pending_days(&self) -> AocResult<Vec<PendingDay>> {
+        let unlocked_days = last_unlocked_day(self.year)
+            .ok_or(AocError::InvalidEventYear(self.year))?;
+        let star_map = self.get_star_map()?;
+
+        let pending = (FIRST_PUZZLE_DAY..=unlocked_days)
+            .map(|day| PendingDay {
+                day,
+                stars: star_map.get(&day).copied().unwrap_or(0),
+            })
+            .filter(|pending| pending.stars < 2)
+            .collect();
+
+        Ok(pending)
+    }
+
+    pub fn completion_fraction(&self) -> AocResult<f32> {
+        let unlocked_days = last_unlocked_day(self.year)
+            .ok_or(AocError::InvalidEventYear(self.year))?;
+        let stars: u32 = self
+            .get_star_map()?
+            .into_iter()
+            .filter(|&(day, _)| day <= unlocked_days)
+            .map(|(_, stars)| u32::from(stars))
+            .sum();
+
+        Ok(stars as f32 / (2 * unlocked_days) as f32)
+    }
+
+    pub fn show_calendar(&self) -> AocResult<()> {
+        if self.plain {
+            for (day, stars) in self.get_star_map()? {
+                let unit = if stars == 1 { "star" } else { "stars" };
+                println!("Day {day}: {stars} {unit}");
+            }
+            return Ok(());
+        }
+
+        let calendar_text = self.get_calendar_text(self.output_width)?;
+        println!("\n{calendar_text}");
+        Ok(())
+    }
+
+    pub fn get_calendar_text(&self, width: usize) -> AocResult<String> {
+        if width == 0 {
+            return Err(AocError::InvalidOutputWidth);
+        }
+
+        let calendar_html = self.get_calendar_html()?;
+        Ok(self.timed("render calendar", || {
+            from_read_with_decorator(
+                calendar_html.as_bytes(),
+                width,
+                TrivialDecorator::new(),
+            )
+        }))
+    }
+
+    pub fn get_global_leaderboard(
+        &self,
+    ) -> AocResult<Vec<GlobalLeaderboardEntry>> {
+        self.ensure_day_unlocked()?;
+
+        debug!(
+            "🦌 Fetching global leaderboard for day {}, {}",
+            self.day, self.year
+        );
+
+        let url =
+            format!("{AOC_URL}/{}/leaderboard/day/{}", self.year, self.day);
+        let contents = self.timed("fetch leaderboard", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    url,
+                    self.session_cookie.as_deref(),
+                    "text/html",
+                )?
+                .send()?;
+            check_status(response)?.text().map_err(AocError::from)
+        })?;
+
+        // Users with both stars are listed first, then those with just the
+        // first star
+        let first_star_start = contents
+            .find("leaderboard-daydesc-first")
+            .unwrap_or(contents.len());
+        let entry_regex = Regex::new(
+            r#"(?s)<div class="leaderboard-entry"[^>]*>(.*?)</div>"#,
+        )
+        .unwrap();
+        let rank_regex =
+            Regex::new(r#"<span class="leaderboard-position">\s*(\d+)\)"#)
+                .unwrap();
+        let time_regex =
+            Regex::new(r#"<span class="leaderboard-time">([^<]*)</span>"#)
+                .unwrap();
+        let noise_regex = Regex::new(concat!(
+            r#"(?s)<span class="leaderboard-(position|time)">[^<]*</span>"#,
+            r#"|<a [^>]*class="(supporter|sponsor)-badge"[^>]*>[^<]*</a>"#,
+        ))
+        .unwrap();
+
+        let entries = entry_regex
+            .captures_iter(&contents)
+            .filter_map(|captures| {
+                let entry = captures.get(1)?;
+                let part = if entry.start() < first_star_start {
+                    PuzzlePart::PartTwo
+                } else {
+                    PuzzlePart::PartOne
+                };
+                let rank =
+                    rank_regex.captures(entry.as_str())?[1].parse().ok()?;
+                let time = time_regex.captures(entry.as_str())?[1].to_string();
+                let name = noise_regex.replace_all(entry.as_str(), "");
+                let name = unescape_html(&name).trim().to_string();
+                Some(GlobalLeaderboardEntry {
+                    part,
+                    rank,
+                    time,
+                    name,
+                })
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    pub fn show_global_leaderboard(&self) -> AocResult<()> {
+        let entries = self.get_global_leaderboard()?;
+        if entries.is_empty() {
+            println!("Nobody is on the leaderboard yet.");
+            return Ok(());
+        }
+
+        for part in [PuzzlePart::PartTwo, PuzzlePart::PartOne] {
+            let title = match part {
+                PuzzlePart::PartOne => {
+                    "First hundred users to get the first star"
+                }
+                PuzzlePart::PartTwo => "First hundred users to get both stars",
+            };
+            let title = if self.plain {
+                title.normal()
+            } else {
+                title.bold()
+            };
+            println!("\n{title} on day {}, {}:\n", self.day, self.year);
+
+            for entry in entries.iter().filter(|entry| entry.part == part) {
+                println!("{:3}) {}  {}", entry.rank, entry.time, entry.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_private_leaderboard_json(
+        &self,
+        leaderboard_id: LeaderboardId,
+    ) -> AocResult<String> {
+        debug!("🦌 Fetching private leaderboard {leaderboard_id}");
+
+        let url = format!(
+            "{AOC_URL}/{}/leaderboard/private/view/{leaderboard_id}.json",
+            self.year,
+        );
+        self.timed("fetch leaderboard", || {
+            let response = self
+                .request(
+                    Method::GET,
+                    url,
+                    Some(self.require_session_cookie()?),
+                    "application/json",
+                )?
+                .send()?;
+
+            // Redirects are not followed, so a 302 is not an error status
+            // and has to be checked before check_status
+            if let Some(err) =
+                private_leaderboard_error(response.status(), leaderboard_id)
+            {
+                return Err(err);
+            }
+
+            check_status(response)?.text().map_err(AocError::from)
+        })
+    }
+
+    fn get_private_leaderboard(
+        &self,
+        leaderboard_id: LeaderboardId,
+    ) -> AocResult<PrivateLeaderboard> {
+        let json = self.get_private_leaderboard_json(leaderboard_id)?;
+        serde_json::from_str(&json).map_err(|_| AocError::AocResponseError)
+    }
+
+    // One row per member, or with full one row per star collected by each
+    // member (in long format, for pivot tables). The columns are:
+    // member_id,name,local_score,stars and, with full, day,part,completed_at
+    pub fn get_private_leaderboard_csv(
+        &self,
+        leaderboard_id: LeaderboardId,
+        full: bool,
+    ) -> AocResult<String> {
+        let leaderboard = self.get_private_leaderboard(leaderboard_id)?;
+        let mut members: Vec<_> = leaderboard.members.values().collect();
+        members.sort_by_key(|member| member.id);
+
+        let mut csv = String::from("member_id,name,local_score,stars");
+        if full {
+            csv.push_str(",day,part,completed_at");
+        }
+        csv.push('\n');
+
+        for member in members {
+            let summary = format!(
+                "{},{},{},{}",
+                member.id,
+                csv_field(&member.get_name()),
+                member.local_score,
+                member.total_stars()
+            );
+            if !full {
+                csv.push_str(&format!("{summary}\n"));
+                continue;
+            }
+
+            let mut stars: Vec<_> = member
+                .completion_day_level
+                .iter()
+                .flat_map(|(day, level)| {
+                    level.iter().map(move |(part, star)| (day, part, star))
+                })
+                .collect();
+            stars.sort_by_key(|&(day, part, _)| (*day, part.clone()));
+            for (day, part, star) in stars {
+                let completed_at = Utc
+                    .timestamp_opt(star.get_star_ts, 0)
+                    .single()
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default();
+                csv.push_str(&format!(
+                    "{summary},{day},{part},{completed_at}\n"
+                ));
+            }
+        }
+
+        Ok(csv)
+    }
+
+    pub fn show_private_leaderboard(
+        &self,
+        leaderboard_id: LeaderboardId,
+    ) -> AocResult<()> {
+        let last_unlocked_day = last_unlocked_day(self.year)
+            .ok_or(AocError::InvalidEventYear(self.year))?;
+        let leaderboard = self.get_private_leaderboard(leaderboard_id)?;
+        let owner_name = leaderboard
+            .get_owner_name()
+            .ok_or(AocError::AocResponseError)?;
+
+        let mut members: Vec<_> = leaderboard.members.values().collect();
+        members.sort_by(|a, b| match self.leaderboard_tiebreak {
+            LeaderboardTiebreak::Id => b.cmp(a),
+            LeaderboardTiebreak::Time => b
+                .local_score
+                .cmp(&a.local_score)
+                .then(a.last_star_ts.cmp(&b.last_star_ts))
+                .then(b.cmp(a)),
+        });
+
+        if self.plain {
+            println!(
+                "Private leaderboard of {owner_name} for Advent of Code {}.\n",
+                self.year
+            );
+            for (member, rank) in members.iter().zip(1..) {
+                println!(
+                    "{rank}) {}: {} points, {} stars",
+                    member.get_name(),
+                    member.local_score,
+                    member.total_stars(),
+                );
+            }
+            return Ok(());
+        }
+
+        let glyphs = self.star_glyphs.clone().unwrap_or_default();
+        let none = match self.star_glyphs {
+            Some(_) => format!("gray {}", glyphs.none),
+            None => "gray dot (.)".to_string(),
+        };
+        println!(
+            "Private leaderboard of {} for Advent of Code {}.\n\n\
+            {} indicates the user got both stars for that day,\n\
+            {} means just the first star, and a {} means none.\n",
+            owner_name.bold(),
+            self.year.to_string().bold(),
+            format!("Gold {}", glyphs.both).color(GOLD),
+            format!("silver {}", glyphs.first).color(SILVER),
+            none.color(DARK_GRAY),
+        );
+
+        let highest_score = members.first().map(|m| m.local_score).unwrap_or(0);
+        let score_width = highest_score.to_string().len();
+        let highest_rank = 1 + leaderboard.members.len();
+        let rank_width = highest_rank.to_string().len();
+        let header_pad: String =
+            vec![' '; rank_width + score_width].into_iter().collect();
+
+        for header in ["         1111111111222222", "1234567890123456789012345"]
+        {
+            let (on, off) = header.split_at(last_unlocked_day as usize);
+            println!("{header_pad}   {}{}", on, off.color(DARK_GRAY));
+        }
+
+        for (member, rank) in members.iter().zip(1..) {
+            let stars: String = (FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY)
+                .map(|day| {
+                    if day > last_unlocked_day {
+                        " ".normal()
+                    } else {
+                        match member.count_stars(day) {
+                            2 => glyphs.both.color(GOLD),
+                            1 => glyphs.first.color(SILVER),
+                            _ => glyphs.none.color(DARK_GRAY),
+                        }
+                    }
+                    .to_string()
+                })
+                .collect();
+
+            println!(
+                "{rank:rank_width$}) {:score_width$} {stars}  {}",
+                member.local_score,
+                member.get_name(),
+            );
+        }
+
+        Ok(())
+    }
+
+    // Headers are set on each request so that they also apply to clients
+    // provided with AocClientBuilder::with_http_client
+    fn request<U: IntoUrl>(
+        &self,
+        method: Method,
+        url: U,
+        session_cookie: Option<&str>,
+        content_type: &str,
+    ) -> AocResult<RequestBuilder> {
+        let content_type_header = HeaderValue::from_str(content_type).unwrap();
+        let user_agent = format!("{PKG_REPO} {PKG_VERSION}");
+        let user_agent_header = HeaderValue::from_str(&user_agent).unwrap();
+
+        // Extra headers can add to the defaults below but not replace them
+        let mut headers = self.extra_headers.clone();
+        if let Some(session_cookie) = session_cookie {
+            let cookie_header = HeaderValue::from_str(&format!(
+                "session={}",
+                session_cookie.trim()
+            ))
+            .map_err(|_| AocError::InvalidSessionCookie)?;
+            headers.insert(COOKIE, cookie_header);
+        }
+        headers.insert(CONTENT_TYPE, content_type_header);
+        headers.insert(USER_AGENT, user_agent_header);
+
+        Ok(self.http_client.request(method, url).headers(headers))
+    }
+
+    fn html2text(&self, html: &str) -> String {
+        self.timed("render text", || {
+            if self.show_html_markup {
+                from_read(html.as_bytes(), self.output_width)
+            } else if self.emphasis {
+                from_read_rich(html.as_bytes(), self.output_width)
+                    .iter()
+                    .map(|line| {
+                        line.tagged_strings()
+                            .map(|tagged| emphasize(&tagged.s, &tagged.tag))
+                            .collect::<String>()
+                            + "\n"
+                    })
+                    .collect()
+            } else {
+                from_read_with_decorator(
+                    html.as_bytes(),
+                    self.output_width,
+                    TrivialDecorator::new(),
+                )
+            }
+        })
+    }
+}
+
+impl Default for AocClientBuilder {
+    fn default() -> Self {
+        let session_cookie = None;
+        let year = None;
+        let day = None;
+        let output_context = OutputContext::detect();
+        let output_width = output_context.width;
+        let overwrite_files = false;
+        let input_filename = DEFAULT_INPUT_FILENAME.into();
+        let puzzle_filename = DEFAULT_PUZZLE_FILENAME.into();
+        let show_html_markup = false;
+        let concise = false;
+        let leaderboard_tiebreak = LeaderboardTiebreak::Id;
+        let anonymous = false;
+        let mirror_dir = None;
+        let plain = false;
+        let markdown_width = None;
+        let reject_distant_puzzles = false;
+        let star_glyphs = None;
+        let http2_prior_knowledge = false;
+        let pool_idle_timeout = None;
+        let emphasis = false;
+        let shared_cache_dir = None;
+        let extra_headers = HeaderMap::new();
+        let input_checksum = false;
+        let strip_answer_label = false;
+        let http_client = None;
+        let session_cookie_source = None;
+        let main_fallback = false;
+        let strict_session_permissions = false;
+        let markdown_flavor = MarkdownFlavor::Gfm;
+        let compress_input = false;
+        let wait_retries = 0;
+
+        Self {
+            session_cookie,
+            year,
+            day,
+            output_width,
+            overwrite_files,
+            input_filename,
+            puzzle_filename,
+            show_html_markup,
+            concise,
+            leaderboard_tiebreak,
+            anonymous,
+            mirror_dir,
+            plain,
+            markdown_width,
+            reject_distant_puzzles,
+            star_glyphs,
+            http2_prior_knowledge,
+            pool_idle_timeout,
+            emphasis,
+            shared_cache_dir,
+            extra_headers,
+            input_checksum,
+            strip_answer_label,
+            http_client,
+            session_cookie_source,
+            main_fallback,
+            strict_session_permissions,
+            markdown_flavor,
+            compress_input,
+            wait_retries,
+            output_context,
+        }
+    }
+}
+
+impl AocClientBuilder {
+    pub fn build(&self) -> AocResult<AocClient> {
+        for (missing, field) in [
+            (
+                self.session_cookie.is_none() && !self.anonymous,
+                "session cookie",
+            ),
+            (self.year.is_none(), "year"),
+            (self.day.is_none(), "day"),
+        ] {
+            if missing {
+                return Err(AocError::ClientFieldMissing(field.to_string()));
+            }
+        }
+
+        let unlock_datetime =
+            unlock_datetime(self.year.unwrap(), self.day.unwrap())?;
+
+        // A puzzle this far away is more likely a typo than a plan
+        if self.reject_distant_puzzles
+            && unlock_datetime.signed_duration_since(Utc::now())
+                > chrono::Duration::days(365)
+        {
+            return Err(AocError::DistantPuzzle(
+                self.day.unwrap(),
+                self.year.unwrap(),
+            ));
+        }
+
+        let http_client = match &self.http_client {
+            Some(http_client) => http_client.clone(),
+            None => {
+                let mut builder =
+                    HttpClient::builder().redirect(Policy::none());
+                if self.http2_prior_knowledge {
+                    builder = builder.http2_prior_knowledge();
+                }
+                if let Some(timeout) = self.pool_idle_timeout {
+                    builder = builder.pool_idle_timeout(timeout);
+                }
+                builder.build()?
+            }
+        };
+
+        Ok(AocClient {
+            session_cookie: self
+                .session_cookie
+                .clone()
+                .filter(|_| !self.anonymous),
+            unlock_datetime,
+            year: self.year.unwrap(),
+            day: self.day.unwrap(),
+            output_width: self.output_width,
+            overwrite_files: self.overwrite_files,
+            input_filename: self.input_filename.clone(),
+            puzzle_filename: self.puzzle_filename.clone(),
+            show_html_markup: self.show_html_markup,
+            concise: self.concise,
+            leaderboard_tiebreak: self.leaderboard_tiebreak,
+            mirror_dir: self.mirror_dir.clone(),
+            plain: self.plain,
+            markdown_width: self.markdown_width,
+            star_glyphs: self.star_glyphs.clone(),
+            emphasis: self.emphasis,
+            shared_cache_dir: self.shared_cache_dir.clone(),
+            extra_headers: self.extra_headers.clone(),
+            input_checksum: self.input_checksum,
+            strip_answer_label: self.strip_answer_label,
+            http_client,
+            session_cookie_source: self
+                .session_cookie_source
+                .clone()
+                .filter(|_| !self.anonymous),
+            main_fallback: self.main_fallback,
+            markdown_flavor: self.markdown_flavor,
+            compress_input: self.compress_input,
+            wait_retries: self.wait_retries,
+            output_context: self.output_context,
+            timings: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn session_cookie(
+        &mut self,
+        session_cookie: impl AsRef<str>,
+    ) -> AocResult<&mut Self> {
+        let cookie = session_cookie.as_ref().trim();
+        if cookie.is_empty() || !cookie.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AocError::InvalidSessionCookie);
+        }
+        self.session_cookie = Some(cookie.to_string());
+        self.session_cookie_source = Some("given value".to_string());
+        Ok(self)
+    }
+
+    pub fn session_cookie_from_default_locations(
+        &mut self,
+    ) -> AocResult<&mut Self> {
+        if let Ok(cookie) = env::var(SESSION_COOKIE_ENV_VAR) {
+            if !cookie.trim().is_empty() {
+                debug!(
+                    "🍪 Loading session cookie from '{SESSION_COOKIE_ENV_VAR}' \
+                    environment variable"
+                );
+
+                self.session_cookie(&cookie)?;
+                self.session_cookie_source = Some(format!(
+                    "'{SESSION_COOKIE_ENV_VAR}' environment variable"
+                ));
+                return Ok(self);
+            }
+
+            warn!(
+                "🍪 Environment variable '{SESSION_COOKIE_ENV_VAR}' is set \
+                but it is empty, ignoring"
+            );
+        }
+
+        if let Ok(file) = env::var(SESSION_FILE_ENV_VAR) {
+            if !file.trim().is_empty() {
+                debug!(
+                    "🍪 Using session cookie file from \
+                    '{SESSION_FILE_ENV_VAR}' environment variable"
+                );
+
+                return self.session_cookie_from_file(file.trim());
+            }
+
+            warn!(
+                "🍪 Environment variable '{SESSION_FILE_ENV_VAR}' is set \
+                but it is empty, ignoring"
+            );
+        }
+
+        let path = if let Some(home_path) = home_dir()
+            .map(|dir| dir.join(HIDDEN_SESSION_COOKIE_FILE))
+            .filter(|file| file.exists())
+        {
+            home_path
+        } else if let Some(config_path) = config_dir()
+            .map(|dir| dir.join(SESSION_COOKIE_FILE))
+            .filter(|file| file.exists())
+        {
+            config_path
+        } else {
+            return Err(AocError::SessionFileNotFound);
+        };
+
+        self.session_cookie_from_file(path)
+    }
+
+    pub fn session_cookie_from_file<P: AsRef<Path>>(
+        &mut self,
+        file: P,
+    ) -> AocResult<&mut Self> {
+        check_session_file_permissions(
+            file.as_ref(),
+            self.strict_session_permissions,
+        )?;
+        let cookie = read_to_string(&file).map_err(|err| {
+            AocError::SessionFileReadError {
+                filename: file.as_ref().display().to_string(),
+                source: err,
+            }
+        })?;
+
+        debug!(
+            "🍪 Loading session cookie from '{}'",
+            file.as_ref().display()
+        );
+        self.session_cookie(&cookie)?;
+        self.session_cookie_source =
+            Some(format!("file '{}'", file.as_ref().display()));
+        Ok(self)
+    }
+
+    pub fn session_cookie_from_command(
+        &mut self,
+        command: impl AsRef<str>,
+    ) -> AocResult<&mut Self> {
+        let command = command.as_ref();
+        let cookie = run_command(command, None).map_err(|err| {
+            AocError::SessionCommandError {
+                command: command.to_string(),
+                source: err,
+            }
+        })?;
+
+        debug!("🍪 Loading session cookie from command '{command}'");
+        self.session_cookie(cookie)?;
+        self.session_cookie_source = Some(format!("command '{command}'"));
+        Ok(self)
+    }
+
+    pub fn year(&mut self, year: PuzzleYear) -> AocResult<&mut Self> {
+        if year >= FIRST_EVENT_YEAR {
+            self.year = Some(year);
+            Ok(self)
+        } else {
+            Err(AocError::InvalidEventYear(year))
+        }
+    }
+
+    pub fn latest_event_year(&mut self) -> AocResult<&mut Self> {
+        let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
+            .unwrap()
+            .from_utc_datetime(&Utc::now().naive_utc());
+
+        let year = if now.month() < DECEMBER {
+            now.year() - 1
+        } else {
+            now.year()
+        };
+
+        self.year(year)
+    }
+
+    pub fn day(&mut self, day: PuzzleDay) -> AocResult<&mut Self> {
+        if (FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY).contains(&day) {
+            self.day = Some(day);
+            Ok(self)
+        } else {
+            Err(AocError::InvalidPuzzleDay(day))
+        }
+    }
+
+    pub fn latest_puzzle_day(&mut self) -> AocResult<&mut Self> {
+        if self.year.is_none() {
+            self.latest_event_year()?;
+        }
+
+        let event_year = self.year.unwrap();
+        let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
+            .unwrap()
+            .from_utc_datetime(&Utc::now().naive_utc());
+
+        if event_year == now.year() && now.month() == DECEMBER {
+            if now.day() <= LAST_PUZZLE_DAY {
+                self.day(now.day())
+            } else {
+                self.day(LAST_PUZZLE_DAY)
+            }
+        } else if event_year < now.year() {
+            // For past events, return the last puzzle day
+            self.day(LAST_PUZZLE_DAY)
+        } else {
+            // For future events, return the first puzzle day
+            self.day(FIRST_PUZZLE_DAY)
+        }
+    }
+
+    pub fn latest_solved_day(&mut self) -> AocResult<&mut Self> {
+        if self.year.is_none() {
+            self.latest_event_year()?;
+        }
+
+        // Unlike the other day resolvers, this fetches the calendar
+        let star_map = self.day(FIRST_PUZZLE_DAY)?.build()?.get_star_map()?;
+        let day = star_map
+            .into_iter()
+            .filter(|&(_, stars)| stars > 0)
+            .map(|(day, _)| day)
+            .max()
+            .unwrap_or(FIRST_PUZZLE_DAY);
+
+        self.day(day)
+    }
+
+    pub fn output_width(&mut self, width: usize) -> AocResult<&mut Self> {
+        if width > 0 {
+            self.output_width = width;
+            Ok(self)
+        } else {
+            Err(AocError::InvalidOutputWidth)
+        }
+    }
+
+    pub fn overwrite_files(&mut self, overwrite: bool) -> &mut Self {
+        self.overwrite_files = overwrite;
+        self
+    }
+
+    pub fn input_filename<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.input_filename = path.as_ref().into();
+        self
+    }
+
+    pub fn puzzle_filename<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.puzzle_filename = path.as_ref().into();
+        self
+    }
+
+    pub fn layout<P: AsRef<Path>>(
         &mut self,
-        file: P,
+        dir: P,
+        layout: FileLayout,
     ) -> AocResult<&mut Self> {
-        let cookie = read_to_string(&file).map_err(|err| {
-            AocError::SessionFileReadError {
-                filename: file.as_ref().display().to_string(),
-                source: err,
+        let year = self
+            .year
+            .ok_or(AocError::ClientFieldMissing("year".to_string()))?;
+        let day = self
+            .day
+            .ok_or(AocError::ClientFieldMissing("day".to_string()))?;
+
+        self.input_filename = layout.input_filename(&dir, year, day);
+        self.puzzle_filename = layout.puzzle_filename(&dir, year, day);
+        Ok(self)
+    }
+
+    pub fn show_html_markup(&mut self, show: bool) -> &mut Self {
+        self.show_html_markup = show;
+        self
+    }
+
+    pub fn concise(&mut self, concise: bool) -> &mut Self {
+        self.concise = concise;
+        self
+    }
+
+    pub fn leaderboard_tiebreak(
+        &mut self,
+        tiebreak: LeaderboardTiebreak,
+    ) -> &mut Self {
+        self.leaderboard_tiebreak = tiebreak;
+        self
+    }
+
+    pub fn reject_distant_puzzles(&mut self, reject: bool) -> &mut Self {
+        self.reject_distant_puzzles = reject;
+        self
+    }
+
+    pub fn anonymous(&mut self, anonymous: bool) -> &mut Self {
+        self.anonymous = anonymous;
+        self
+    }
+
+    pub fn mirror_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.mirror_dir = Some(dir.as_ref().into());
+        self
+    }
+
+    pub fn plain(&mut self, plain: bool) -> &mut Self {
+        self.plain = plain;
+        self
+    }
+
+    pub fn markdown_width(
+        &mut self,
+        width: Option<usize>,
+    ) -> AocResult<&mut Self> {
+        if width == Some(0) {
+            return Err(AocError::InvalidOutputWidth);
+        }
+        self.markdown_width = width;
+        Ok(self)
+    }
+
+    pub fn star_glyphs(&mut self, glyphs: StarGlyphs) -> &mut Self {
+        self.star_glyphs = Some(glyphs);
+        self
+    }
+
+    pub fn http2_prior_knowledge(&mut self, enabled: bool) -> &mut Self {
+        self.http2_prior_knowledge = enabled;
+        self
+    }
+
+    pub fn pool_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn emphasis(&mut self, emphasis: bool) -> &mut Self {
+        self.emphasis = emphasis;
+        self
+    }
+
+    pub fn shared_cache_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.shared_cache_dir = Some(dir.as_ref().into());
+        self
+    }
+
+    pub fn extra_headers(&mut self, headers: HeaderMap) -> &mut Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    pub fn extra_header(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> AocResult<&mut Self> {
+        let invalid_header = || AocError::InvalidHeader(name.to_string());
+        let header_name =
+            HeaderName::from_str(name).map_err(|_| invalid_header())?;
+        let header_value =
+            HeaderValue::from_str(value).map_err(|_| invalid_header())?;
+        self.extra_headers.insert(header_name, header_value);
+        Ok(self)
+    }
+
+    pub fn input_checksum(&mut self, checksum: bool) -> &mut Self {
+        self.input_checksum = checksum;
+        self
+    }
+
+    pub fn strip_answer_label(&mut self, strip: bool) -> &mut Self {
+        self.strip_answer_label = strip;
+        self
+    }
+
+    // Requests go through this client instead of one built by the crate,
+    // which still sets the session cookie and other headers on each request.
+    // Its redirect policy should be Policy::none() as redirects are checked
+    // for, and http2_prior_knowledge and pool_idle_timeout don't apply.
+    pub fn with_http_client(&mut self, http_client: HttpClient) -> &mut Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    pub fn main_fallback(&mut self, fallback: bool) -> &mut Self {
+        self.main_fallback = fallback;
+        self
+    }
+
+    // Session cookie files readable by other users are reported with a
+    // warning, or with an error if strict. Must be set before reading one.
+    pub fn strict_session_permissions(&mut self, strict: bool) -> &mut Self {
+        self.strict_session_permissions = strict;
+        self
+    }
+
+    pub fn markdown_flavor(&mut self, flavor: MarkdownFlavor) -> &mut Self {
+        self.markdown_flavor = flavor;
+        self
+    }
+
+    pub fn compress_input(&mut self, compress: bool) -> &mut Self {
+        self.compress_input = compress;
+        self
+    }
+
+    // Answers submitted too soon are resubmitted once the wait asked for by
+    // the website is over, up to this many times
+    pub fn wait_retries(&mut self, retries: u32) -> &mut Self {
+        self.wait_retries = retries;
+        self
+    }
+
+    // Also sets the output width, so an explicit width must come after it.
+    pub fn output_context(&mut self, context: OutputContext) -> &mut Self {
+        self.output_context = context;
+        self.output_width = context.width;
+        self
+    }
+
+    // The settings resolved so far, to show them without building a client,
+    // which would need every one of them
+    pub fn session_cookie_source(&self) -> Option<&str> {
+        self.session_cookie_source
+            .as_deref()
+            .filter(|_| !self.anonymous)
+    }
+
+    pub fn resolved_year(&self) -> Option<PuzzleYear> {
+        self.year
+    }
+
+    pub fn resolved_day(&self) -> Option<PuzzleDay> {
+        self.day
+    }
+
+    pub fn resolved_output_width(&self) -> usize {
+        self.output_width
+    }
+
+    pub fn saved_input_filename(&self) -> PathBuf {
+        saved_input_path(&self.input_filename, self.compress_input)
+    }
+
+    pub fn resolved_puzzle_filename(&self) -> &Path {
+        &self.puzzle_filename
+    }
+}
+
+// The core operations of AocClient, for code that wants to swap in a fake
+// client in its tests instead of talking to adventofcode.com
+pub trait AocApi {
+    fn year(&self) -> PuzzleYear;
+    fn day(&self) -> PuzzleDay;
+    fn day_unlocked(&self) -> bool;
+    fn get_puzzle_html(&self) -> AocResult<String>;
+    fn get_puzzle_text(&self) -> AocResult<String>;
+    fn get_input(&self) -> AocResult<String>;
+    fn solved_parts(&self) -> AocResult<Vec<PuzzlePart>>;
+    fn submit_answer(
+        &self,
+        part: PuzzlePart,
+        answer: &str,
+    ) -> AocResult<SubmissionOutcome>;
+}
+
+impl AocApi for AocClient {
+    fn year(&self) -> PuzzleYear {
+        AocClient::year(self)
+    }
+
+    fn day(&self) -> PuzzleDay {
+        AocClient::day(self)
+    }
+
+    fn day_unlocked(&self) -> bool {
+        AocClient::day_unlocked(self)
+    }
+
+    fn get_puzzle_html(&self) -> AocResult<String> {
+        AocClient::get_puzzle_html(self)
+    }
+
+    fn get_puzzle_text(&self) -> AocResult<String> {
+        AocClient::get_puzzle_text(self)
+    }
+
+    fn get_input(&self) -> AocResult<String> {
+        AocClient::get_input(self)
+    }
+
+    fn solved_parts(&self) -> AocResult<Vec<PuzzlePart>> {
+        AocClient::solved_parts(self)
+    }
+
+    fn submit_answer(
+        &self,
+        part: PuzzlePart,
+        answer: &str,
+    ) -> AocResult<SubmissionOutcome> {
+        AocClient::submit_answer(self, part.to_string().as_str(), answer)
+    }
+}
+
+pub fn last_unlocked_day(year: PuzzleYear) -> Option<PuzzleDay> {
+    let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
+        .unwrap()
+        .from_utc_datetime(&Utc::now().naive_utc());
+
+    if year == now.year() && now.month() == DECEMBER {
+        if now.day() > LAST_PUZZLE_DAY {
+            Some(LAST_PUZZLE_DAY)
+        } else {
+            Some(now.day())
+        }
+    } else if year >= FIRST_EVENT_YEAR && year < now.year() {
+        Some(LAST_PUZZLE_DAY)
+    } else {
+        None
+    }
+}
+
+// When the next locked day of an event unlocks, or, once every day of it has
+// unlocked, when the first day of the following event does (None if that's
+// in the past too)
+pub fn next_unlock(year: PuzzleYear) -> Option<DateTime<FixedOffset>> {
+    let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
+        .unwrap()
+        .from_utc_datetime(&Utc::now().naive_utc());
+
+    (FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY)
+        .filter_map(|day| unlock_datetime(year, day).ok())
+        .chain(unlock_datetime(year + 1, FIRST_PUZZLE_DAY).ok())
+        .find(|unlock| *unlock > now)
+}
+
+// Points of a star on a private leaderboard: the first member to get it
+// scores as many points as there are members, the next one less and so on
+pub fn private_leaderboard_points(member_count: u64, rank: u64) -> u64 {
+    if rank == 0 {
+        return 0;
+    }
+    (member_count + 1).saturating_sub(rank)
+}
+
+fn ensure_unlocked(year: PuzzleYear, day: PuzzleDay) -> AocResult<()> {
+    if year < FIRST_EVENT_YEAR {
+        return Err(AocError::InvalidEventYear(year));
+    }
+    if !(FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY).contains(&day) {
+        return Err(AocError::InvalidPuzzleDay(day));
+    }
+
+    let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
+        .unwrap()
+        .from_utc_datetime(&Utc::now().naive_utc());
+    if unlock_datetime(year, day)? > now {
+        return Err(AocError::LockedPuzzle(day, year));
+    }
+    Ok(())
+}
+
+fn unlock_datetime(
+    year: PuzzleYear,
+    day: PuzzleDay,
+) -> AocResult<DateTime<FixedOffset>> {
+    let timezone = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET).unwrap();
+    let local_datetime = NaiveDate::from_ymd_opt(year, DECEMBER, day)
+        .ok_or(AocError::InvalidPuzzleDate(day, year))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    timezone
+        .from_local_datetime(&local_datetime)
+        .single()
+        .ok_or(AocError::InvalidPuzzleDate(day, year))
+}
+
+fn puzzle_title(puzzle_html: &str) -> Option<String> {
+    Regex::new(r"<h2>--- Day \d+: (?P<title>.*?) ---</h2>")
+        .unwrap()
+        .captures(puzzle_html)
+        .map(|captures| unescape_html(&captures["title"]))
+}
+
+// Lowercase words joined by hyphens, without punctuation
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Best effort: pairs each example block with the last highlighted value
+// before the next block, as in "the answer is <code><em>142</em></code>".
+// Unusual phrasings may give the wrong value or no value at all.
+fn example_answers(puzzle_html: &str) -> Vec<(String, Option<String>)> {
+    let example_regex =
+        Regex::new(r"(?s)<pre><code>(?P<example>.*?)</code></pre>").unwrap();
+    let answer_regex =
+        Regex::new(r"(?s)<code><em>(?P<answer>.*?)</em></code>").unwrap();
+
+    let examples: Vec<_> = example_regex.captures_iter(puzzle_html).collect();
+    examples
+        .iter()
+        .enumerate()
+        .map(|(index, captures)| {
+            let start = captures.get(0).unwrap().end();
+            let end = examples
+                .get(index + 1)
+                .map(|next| next.get(0).unwrap().start())
+                .unwrap_or(puzzle_html.len());
+            let answer = answer_regex
+                .captures_iter(&puzzle_html[start..end])
+                .last()
+                .map(|answer| unescape_html(&answer["answer"]));
+            (unescape_html(&captures["example"]), answer)
+        })
+        .collect()
+}
+
+// Pages of answers given too soon say e.g. "You have 4m 31s left to wait"
+// or "You have one minute left to wait"
+fn wait_time(outcome_html: &str) -> Option<Duration> {
+    if !outcome_html.contains("You gave an answer too recently") {
+        return None;
+    }
+
+    let captures = Regex::new(concat!(
+        r"You have (?:(?P<one_minute>one minute)|",
+        r"(?:(?P<minutes>[0-9]+)m )?(?P<seconds>[0-9]+)s) left to wait",
+    ))
+    .unwrap()
+    .captures(outcome_html)?;
+    if captures.name("one_minute").is_some() {
+        return Some(Duration::from_secs(60));
+    }
+    let minutes: u64 = captures
+        .name("minutes")
+        .map_or(Some(0), |minutes| minutes.as_str().parse().ok())?;
+    let seconds: u64 = captures.name("seconds")?.as_str().parse().ok()?;
+
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum WaitRetry {
+    // Resubmit after waiting this long
+    After(Duration),
+    // Waiting this long would go past MAX_WAIT_RETRY_TIME
+    TooLong(Duration),
+    // Not asked to wait, or out of retries
+    Stop,
+}
+
+// Whether an answer given too soon is resubmitted, with a margin on top of
+// the wait asked for in case clocks differ
+fn wait_retry(
+    wait: Option<Duration>,
+    retries: u32,
+    max_retries: u32,
+    waited: Duration,
+) -> WaitRetry {
+    let wait = match wait {
+        Some(wait) if retries < max_retries => wait + WAIT_RETRY_MARGIN,
+        _ => return WaitRetry::Stop,
+    };
+    if waited + wait > MAX_WAIT_RETRY_TIME {
+        WaitRetry::TooLong(wait)
+    } else {
+        WaitRetry::After(wait)
+    }
+}
+
+fn puzzle_answers(puzzle_html: &str) -> Vec<String> {
+    // Each solved part is followed by "Your puzzle answer was ..."
+    Regex::new(r"Your puzzle answer was <code>(?P<answer>.*?)</code>")
+        .unwrap()
+        .captures_iter(puzzle_html)
+        .map(|captures| unescape_html(&captures["answer"]))
+        .collect()
+}
+
+// The statement of each unlocked part is in its own article
+fn puzzle_articles(puzzle_html: &str) -> Vec<&str> {
+    Regex::new(r#"(?s)<article class="day-desc">.*?</article>"#)
+        .unwrap()
+        .find_iter(puzzle_html)
+        .map(|article| article.as_str())
+        .collect()
+}
+
+fn none_if_locked<T>(result: AocResult<T>) -> AocResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(AocError::LockedPuzzle(..)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn first_parts(count: usize) -> Vec<PuzzlePart> {
+    [PuzzlePart::PartOne, PuzzlePart::PartTwo]
+        .into_iter()
+        .take(count)
+        .collect()
+}
+
+// Reflows paragraphs and list items to the given width, leaving headings,
+// tables and code blocks untouched
+fn wrap_markdown(markdown: &str, width: usize) -> String {
+    let prefix_regex = Regex::new(r"^\s*(?:(?:[*+-]|\d+\.)\s+)?").unwrap();
+    let mut wrapped = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block
+            || line.trim_start().starts_with("```")
+            || line.starts_with("    ")
+            || line.starts_with('#')
+            || line.starts_with('|')
+            || line.chars().count() <= width
+        {
+            wrapped.push_str(line);
+            wrapped.push('\n');
+            continue;
+        }
+
+        // Continuation lines are indented to line up with the first word
+        let prefix_len = prefix_regex.find(line).map_or(0, |m| m.end());
+        let indent = " ".repeat(line[..prefix_len].chars().count());
+        let mut current = line[..prefix_len].to_string();
+        let mut current_len = current.chars().count();
+        let mut has_words = false;
+
+        for word in line[prefix_len..].split_whitespace() {
+            let word_len = word.chars().count();
+            if has_words && current_len + 1 + word_len > width {
+                wrapped.push_str(&current);
+                wrapped.push('\n');
+                current = indent.clone();
+                current_len = indent.len();
+                has_words = false;
             }
-        })?;
+            if has_words {
+                current.push(' ');
+                current_len += 1;
+            }
+            current.push_str(word);
+            current_len += word_len;
+            has_words = true;
+        }
+
+        wrapped.push_str(&current);
+        wrapped.push('\n');
+    }
+
+    wrapped
+}
+
+// Keeps emphasis visible in the terminal, e.g. AoC uses <em> to highlight
+// the values that matter
+fn emphasize(text: &str, annotations: &[RichAnnotation]) -> String {
+    let mut styled = text.normal();
+    for annotation in annotations {
+        styled = match annotation {
+            RichAnnotation::Emphasis => styled.bold(),
+            RichAnnotation::Strong => styled.underline(),
+            _ => styled,
+        };
+    }
+    styled.to_string()
+}
 
-        debug!(
-            "🍪 Loading session cookie from '{}'",
-            file.as_ref().display()
-        );
-        self.session_cookie(&cookie)
+// html2md may escape or reflow the example inputs in <pre><code> blocks, so
+// they're swapped for placeholders and put back verbatim as fenced blocks
+fn html_to_markdown(html: &str) -> String {
+    let code_regex = Regex::new(r"(?s)<pre><code>(.*?)</code></pre>").unwrap();
+    let placeholder = |index| format!("AOCCODEBLOCK{index}PLACEHOLDER");
+
+    let mut code_blocks = Vec::new();
+    let protected_html = code_regex.replace_all(html, |captures: &Captures| {
+        code_blocks.push(unescape_html(&captures[1]));
+        format!("<p>{}</p>", placeholder(code_blocks.len() - 1))
+    });
+
+    let mut markdown = parse_html(&protected_html);
+    for (index, code) in code_blocks.iter().enumerate() {
+        let fenced = format!("```\n{}\n```", code.trim_end_matches('\n'));
+        markdown = markdown.replace(&placeholder(index), &fenced);
     }
+    markdown
+}
 
-    pub fn year(&mut self, year: PuzzleYear) -> AocResult<&mut Self> {
-        if year >= FIRST_EVENT_YEAR {
-            self.year = Some(year);
-            Ok(self)
+// html2md writes GFM, so tables and strikethrough, which CommonMark lacks,
+// are turned into code blocks and <del> tags
+fn gfm_to_commonmark(markdown: &str) -> String {
+    let strikethrough_regex = Regex::new(r"~~(?P<text>.+?)~~").unwrap();
+    let mut converted = String::new();
+    let mut in_code_block = false;
+    let mut in_table = false;
+
+    for line in markdown.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        let is_table_row = !in_code_block && line.starts_with('|');
+        if is_table_row != in_table {
+            converted.push_str("```\n");
+            in_table = is_table_row;
+        }
+        if is_fence {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block || is_fence || in_table {
+            converted.push_str(line);
         } else {
-            Err(AocError::InvalidEventYear(year))
+            converted.push_str(
+                &strikethrough_regex.replace_all(line, "<del>$text</del>"),
+            );
         }
+        converted.push('\n');
+    }
+    if in_table {
+        converted.push_str("```\n");
     }
 
-    pub fn latest_event_year(&mut self) -> AocResult<&mut Self> {
-        let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
-            .unwrap()
-            .from_utc_datetime(&Utc::now().naive_utc());
+    converted
+}
 
-        let year = if now.month() < DECEMBER {
-            now.year() - 1
-        } else {
-            now.year()
-        };
+// Quoted only when needed, with quotes inside doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
 
-        self.year(year)
+fn unescape_html(html: &str) -> String {
+    Regex::new(r"<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// A 503 or a page about maintenance means the site is down, rather than
+// anything being wrong with the request or the session cookie
+fn check_status(response: Response) -> AocResult<Response> {
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+        return Err(AocError::ServiceUnavailable);
     }
+    Ok(response.error_for_status()?)
+}
 
-    pub fn day(&mut self, day: PuzzleDay) -> AocResult<&mut Self> {
-        if (FIRST_PUZZLE_DAY..=LAST_PUZZLE_DAY).contains(&day) {
-            self.day = Some(day);
-            Ok(self)
-        } else {
-            Err(AocError::InvalidPuzzleDay(day))
+fn is_maintenance_page(html: &str) -> bool {
+    Regex::new(concat!(
+        r"(?i)(?:down for|under(?:going)?|scheduled) maintenance",
+        r"|temporarily unavailable",
+    ))
+    .unwrap()
+    .is_match(html)
+}
+
+fn private_leaderboard_error(
+    status: StatusCode,
+    leaderboard_id: LeaderboardId,
+) -> Option<AocError> {
+    match status {
+        // Redirected away since we can't access the leaderboard
+        StatusCode::FOUND => Some(AocError::PrivateLeaderboardNotAvailable),
+        StatusCode::NOT_FOUND => {
+            Some(AocError::PrivateLeaderboardNotFound(leaderboard_id))
         }
+        _ => None,
     }
+}
 
-    pub fn latest_puzzle_day(&mut self) -> AocResult<&mut Self> {
-        if self.year.is_none() {
-            self.latest_event_year()?;
+enum SubmitFailure<'a> {
+    // No connection could be made
+    Connect,
+    // Any other failure to send the answer or get a response
+    Request,
+    // An error status, with the page that came with it
+    Status(StatusCode, &'a str),
+}
+
+// Submissions are not idempotent, so they're only retried when the answer
+// cannot have been scored: the connection failed, or a gateway error came
+// back without an outcome page
+fn may_resubmit(failure: SubmitFailure) -> bool {
+    match failure {
+        SubmitFailure::Connect => true,
+        SubmitFailure::Request => false,
+        SubmitFailure::Status(status, body) => {
+            is_gateway_error(status) && !body.contains("<main>")
         }
+    }
+}
 
-        let event_year = self.year.unwrap();
-        let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
-            .unwrap()
-            .from_utc_datetime(&Utc::now().naive_utc());
+fn is_gateway_error(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
 
-        if event_year == now.year() && now.month() == DECEMBER {
-            if now.day() <= LAST_PUZZLE_DAY {
-                self.day(now.day())
-            } else {
-                self.day(LAST_PUZZLE_DAY)
-            }
-        } else if event_year < now.year() {
-            // For past events, return the last puzzle day
-            self.day(LAST_PUZZLE_DAY)
-        } else {
-            // For future events, return the first puzzle day
-            self.day(FIRST_PUZZLE_DAY)
-        }
+fn count_calendar_stars(class: &str, all_stars: bool) -> u8 {
+    if class.contains("calendar-verycomplete") || all_stars {
+        2
+    } else if class.contains("calendar-complete") {
+        1
+    } else {
+        0
     }
+}
 
-    pub fn output_width(&mut self, width: usize) -> AocResult<&mut Self> {
-        if width > 0 {
-            self.output_width = width;
-            Ok(self)
+fn run_command(
+    command: &str,
+    input: Option<String>,
+) -> std::io::Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+
+    let mut child = shell
+        .stdin(if input.is_some() {
+            Stdio::piped()
         } else {
-            Err(AocError::InvalidOutputWidth)
-        }
+            Stdio::inherit()
+        })
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // Feed the input from a separate thread so that a command producing a
+    // lot of output before reading all its input cannot deadlock
+    let writer = child.stdin.take().zip(input).map(|(mut stdin, input)| {
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        // A command may exit without reading all of its input
+        let _ = writer.join();
     }
 
-    pub fn overwrite_files(&mut self, overwrite: bool) -> &mut Self {
-        self.overwrite_files = overwrite;
-        self
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(std::io::Error::other(format!(
+            "command exited with {}",
+            output.status
+        )))
     }
+}
 
-    pub fn input_filename<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
-        self.input_filename = path.as_ref().into();
-        self
-    }
+// Only Unix has permission bits for group and other users to check
+#[cfg(unix)]
+fn check_session_file_permissions(file: &Path, strict: bool) -> AocResult<()> {
+    use std::os::unix::fs::PermissionsExt;
 
-    pub fn puzzle_filename<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
-        self.puzzle_filename = path.as_ref().into();
-        self
+    let Ok(metadata) = file.metadata() else {
+        // Let reading the file report the error
+        return Ok(());
+    };
+    if metadata.permissions().mode() & 0o077 == 0 {
+        return Ok(());
     }
 
-    pub fn show_html_markup(&mut self, show: bool) -> &mut Self {
-        self.show_html_markup = show;
-        self
+    if strict {
+        return Err(AocError::InsecureSessionFile(file.display().to_string()));
     }
+    warn!(
+        "🍪 Session cookie file '{}' can be read by other users, restrict it \
+        with: chmod 600 '{}'",
+        file.display(),
+        file.display()
+    );
+    Ok(())
 }
 
-pub fn last_unlocked_day(year: PuzzleYear) -> Option<PuzzleDay> {
-    let now = FixedOffset::east_opt(RELEASE_TIMEZONE_OFFSET)
-        .unwrap()
-        .from_utc_datetime(&Utc::now().naive_utc());
+#[cfg(not(unix))]
+fn check_session_file_permissions(
+    _file: &Path,
+    _strict: bool,
+) -> AocResult<()> {
+    Ok(())
+}
 
-    if year == now.year() && now.month() == DECEMBER {
-        if now.day() > LAST_PUZZLE_DAY {
-            Some(LAST_PUZZLE_DAY)
-        } else {
-            Some(now.day())
-        }
-    } else if year >= FIRST_EVENT_YEAR && year < now.year() {
-        Some(LAST_PUZZLE_DAY)
+fn read_file<P: AsRef<Path>>(path: P) -> AocResult<String> {
+    read_to_string(&path).map_err(|err| AocError::FileReadError {
+        filename: path.as_ref().display().to_string(),
+        source: err,
+    })
+}
+
+// Answers are always trimmed. With strip_label, a leading "Answer:" or
+// "Part N:" label (in any case) is removed as well, e.g. from solver output.
+fn normalize_answer(answer: &str, strip_label: bool) -> &str {
+    let answer = answer.trim();
+    if !strip_label {
+        return answer;
+    }
+
+    let Some((label, rest)) = answer.split_once(':') else {
+        return answer;
+    };
+    let label = label.trim().to_lowercase();
+    let is_label = label == "answer"
+        || label.strip_prefix("part").is_some_and(|number| {
+            let number = number.trim();
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        });
+
+    if is_label {
+        rest.trim()
     } else {
-        None
+        answer
     }
 }
 
-fn http_client(
-    session_cookie: &str,
-    content_type: &str,
-) -> AocResult<HttpClient> {
-    let cookie_header =
-        HeaderValue::from_str(&format!("session={}", session_cookie.trim()))
-            .map_err(|_| AocError::InvalidSessionCookie)?;
-    let content_type_header = HeaderValue::from_str(content_type).unwrap();
-    let user_agent = format!("{PKG_REPO} {PKG_VERSION}");
-    let user_agent_header = HeaderValue::from_str(&user_agent).unwrap();
+// A single dash stands for stdout, as is customary in command line tools
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT_FILENAME)
+}
+
+fn saved_input_path(path: &Path, compress: bool) -> PathBuf {
+    let mut path = path.to_path_buf();
+    if compress && !is_stdout(&path) {
+        path.as_mut_os_string().push(".gz");
+    }
+    path
+}
 
-    let mut headers = HeaderMap::new();
-    headers.insert(COOKIE, cookie_header);
-    headers.insert(CONTENT_TYPE, content_type_header);
-    headers.insert(USER_AGENT, user_agent_header);
+// Inputs may be saved gzip-compressed, as <name>.gz, which is read when
+// <name> itself is missing
+fn read_input_file(path: &Path) -> AocResult<String> {
+    let mut gz_path = path.to_path_buf();
+    gz_path.as_mut_os_string().push(".gz");
+    let path = if !path.exists() && gz_path.exists() {
+        gz_path.as_path()
+    } else {
+        path
+    };
+    if path.extension().map_or(true, |extension| extension != "gz") {
+        return read_file(path);
+    }
+
+    let mut input = String::new();
+    File::open(path)
+        .and_then(|file| GzDecoder::new(file).read_to_string(&mut input))
+        .map_err(|err| AocError::FileReadError {
+            filename: path.display().to_string(),
+            source: err,
+        })?;
+    Ok(input)
+}
 
-    HttpClient::builder()
-        .default_headers(headers)
-        .redirect(Policy::none())
-        .build()
-        .map_err(AocError::from)
+fn gzip(contents: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(contents.as_bytes())?;
+    encoder.finish()
 }
 
 fn save_file<P: AsRef<Path>>(
     path: P,
     overwrite: bool,
-    contents: &str,
+    contents: impl AsRef<[u8]>,
 ) -> AocResult<()> {
+    let contents = contents.as_ref();
+    let write_error = |err| AocError::FileWriteError {
+        filename: path.as_ref().to_string_lossy().into(),
+        source: err,
+    };
+
+    if is_stdout(path.as_ref()) {
+        return stdout().write_all(contents).map_err(write_error);
+    }
+
+    if let Some(dir) = path.as_ref().parent() {
+        create_dir_all(dir).map_err(write_error)?;
+    }
+
     let mut file = OpenOptions::new();
     if overwrite {
         file.create(true);
@@ -798,11 +3009,34 @@ fn save_file<P: AsRef<Path>>(
     file.write(true)
         .truncate(true)
         .open(&path)
-        .and_then(|mut file| file.write_all(contents.as_bytes()))
-        .map_err(|err| AocError::FileWriteError {
-            filename: path.as_ref().to_string_lossy().into(),
-            source: err,
-        })
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(write_error)
+}
+
+// Writes to a temporary file next to the target first, so that the target
+// is never left half-written
+fn save_file_atomically<P: AsRef<Path>>(
+    path: P,
+    overwrite: bool,
+    contents: impl AsRef<[u8]>,
+) -> AocResult<()> {
+    let path = path.as_ref();
+    if is_stdout(path) {
+        return save_file(path, overwrite, contents);
+    }
+
+    let write_error = |err| AocError::FileWriteError {
+        filename: path.to_string_lossy().into(),
+        source: err,
+    };
+    if !overwrite && path.exists() {
+        return Err(write_error(ErrorKind::AlreadyExists.into()));
+    }
+
+    let mut temp_path = path.to_path_buf();
+    temp_path.as_mut_os_string().push(".tmp");
+    save_file(&temp_path, true, contents)?;
+    rename(&temp_path, path).map_err(write_error)
 }
 
 #[derive(Deserialize)]
@@ -822,13 +3056,18 @@ struct Member {
     id: MemberId,
     name: Option<String>,
     local_score: Score,
+    #[serde(default)]
+    last_star_ts: Timestamp,
     completion_day_level: HashMap<PuzzleDay, DayLevel>,
 }
 
 type DayLevel = HashMap<String, CollectedStar>;
 
 #[derive(Eq, Deserialize, PartialEq)]
-struct CollectedStar {}
+struct CollectedStar {
+    #[serde(default)]
+    get_star_ts: Timestamp,
+}
 
 impl Member {
     fn get_name(&self) -> String {
@@ -844,6 +3083,13 @@ impl Member {
             .map(|stars| stars.len())
             .unwrap_or(0)
     }
+
+    fn total_stars(&self) -> usize {
+        self.completion_day_level
+            .values()
+            .map(|stars| stars.len())
+            .sum()
+    }
 }
 
 impl Ord for Member {
@@ -867,6 +3113,83 @@ impl PartialEq for Member {
     }
 }
 
+impl OutputContext {
+    // The width is taken from $COLUMNS or the terminal even when output is
+    // redirected, and colours follow the NO_COLOR and CLICOLOR_FORCE
+    // conventions
+    pub fn detect() -> Self {
+        let is_terminal = stdout().is_terminal();
+        let width = env::var(COLUMNS_ENV_VAR)
+            .ok()
+            .and_then(|columns| columns.trim().parse().ok())
+            .filter(|&width| width > 0)
+            .or_else(|| term_size::dimensions().map(|(w, _)| w))
+            .unwrap_or(DEFAULT_COL_WIDTH);
+        let color = env::var(CLICOLOR_FORCE_ENV_VAR)
+            .is_ok_and(|force| force != "0")
+            || (is_terminal && env::var_os(NO_COLOR_ENV_VAR).is_none());
+
+        Self {
+            is_terminal,
+            width,
+            color,
+        }
+    }
+
+    // Colours are added by the colored crate, which otherwise makes up its
+    // own mind about the terminal. It's a process-wide setting, so this is
+    // for applications to call once, not for every client.
+    pub fn apply_color(self) {
+        colored::control::set_override(self.color);
+    }
+}
+
+impl FileLayout {
+    pub fn directory<P: AsRef<Path>>(
+        self,
+        dir: P,
+        year: PuzzleYear,
+        day: PuzzleDay,
+    ) -> PathBuf {
+        match self {
+            Self::Flat => dir.as_ref().to_path_buf(),
+            Self::PerDay => dir.as_ref().join(format!("{day:02}")),
+            Self::YearDay => dir
+                .as_ref()
+                .join(year.to_string())
+                .join(format!("{day:02}")),
+        }
+    }
+
+    pub fn input_filename<P: AsRef<Path>>(
+        self,
+        dir: P,
+        year: PuzzleYear,
+        day: PuzzleDay,
+    ) -> PathBuf {
+        self.directory(dir, year, day).join(DEFAULT_INPUT_FILENAME)
+    }
+
+    pub fn puzzle_filename<P: AsRef<Path>>(
+        self,
+        dir: P,
+        year: PuzzleYear,
+        day: PuzzleDay,
+    ) -> PathBuf {
+        self.directory(dir, year, day).join(DEFAULT_PUZZLE_FILENAME)
+    }
+}
+
+impl Default for StarGlyphs {
+    fn default() -> Self {
+        Self {
+            both: "*".to_string(),
+            first: "*".to_string(),
+            none: ".".to_string(),
+        }
+    }
+}
+
 impl Display for PuzzlePart {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -876,6 +3199,17 @@ impl Display for PuzzlePart {
     }
 }
 
+impl Display for SubmissionOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Correct => write!(f, "correct"),
+            Self::Incorrect => write!(f, "incorrect"),
+            Self::Wait => write!(f, "submitted too soon"),
+            Self::WrongLevel => write!(f, "not the right level"),
+        }
+    }
+}
+
 impl TryFrom<&String> for PuzzlePart {
     type Error = AocError;
 
@@ -907,3 +3241,320 @@ impl TryFrom<i64> for PuzzlePart {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> AocClientBuilder {
+        let mut builder = AocClient::builder();
+        builder.anonymous(true).year(2015).unwrap().day(1).unwrap();
+        builder
+    }
+
+    fn client_with_output(output: OutputContext) -> AocClient {
+        builder().output_context(output).build().unwrap()
+    }
+
+    #[test]
+    fn output_width_comes_from_the_context() {
+        let client = client_with_output(OutputContext {
+            is_terminal: true,
+            width: 120,
+            color: true,
+        });
+        assert_eq!(client.output_context().width, 120);
+        assert_eq!(client.output_width(), 120);
+    }
+
+    #[test]
+    fn redirected_output_is_not_made_plain() {
+        let client = client_with_output(OutputContext {
+            is_terminal: false,
+            width: 100,
+            color: false,
+        });
+        assert!(!client.plain);
+        assert_eq!(client.output_width(), 100);
+    }
+
+    #[test]
+    fn puzzle_part_round_trips_through_json() {
+        for (part, json) in [
+            (PuzzlePart::PartOne, r#""part_one""#),
+            (PuzzlePart::PartTwo, r#""part_two""#),
+        ] {
+            assert_eq!(serde_json::to_string(&part).unwrap(), json);
+            assert_eq!(serde_json::from_str::<PuzzlePart>(json).unwrap(), part);
+        }
+    }
+
+    #[test]
+    fn submission_outcome_round_trips_through_json() {
+        for (outcome, json) in [
+            (SubmissionOutcome::Correct, r#"{"outcome":"correct"}"#),
+            (SubmissionOutcome::Incorrect, r#"{"outcome":"incorrect"}"#),
+            (SubmissionOutcome::Wait, r#"{"outcome":"wait"}"#),
+            (
+                SubmissionOutcome::WrongLevel,
+                r#"{"outcome":"wrong_level"}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&outcome).unwrap(), json);
+            assert_eq!(
+                serde_json::from_str::<SubmissionOutcome>(json).unwrap(),
+                outcome
+            );
+        }
+    }
+
+    const PART_ONE_PAGE: &str = r#"<article class="day-desc">
+<h2>--- Day 1: Trebuchet?! ---</h2>
+<p>For example:</p>
+<pre><code>1abc2
+pqr3stu8vwx
+</code></pre>
+<p>Adding these together produces <code><em>142</em></code>.</p>
+</article>
+<p>Your puzzle answer was <code>54304</code>.</p>"#;
+
+    const BOTH_PARTS_PAGE: &str = r#"<article class="day-desc">
+<h2>--- Day 1: Trebuchet?! ---</h2>
+<p>For example:</p>
+<pre><code>1abc2
+pqr3stu8vwx
+</code></pre>
+<p>Adding these together produces <code><em>142</em></code>.</p>
+</article>
+<p>Your puzzle answer was <code>54304</code>.</p>
+<article class="day-desc"><h2 id="part2">--- Part Two ---</h2>
+<p>For example:</p>
+<pre><code>two1nine
+eightwothree
+</code></pre>
+<p>Adding these together produces <code><em>281</em></code>.</p>
+</article>"#;
+
+    #[test]
+    fn example_answers_of_first_part() {
+        assert_eq!(
+            example_answers(PART_ONE_PAGE),
+            [("1abc2\npqr3stu8vwx\n".to_string(), Some("142".to_string()))]
+        );
+    }
+
+    #[test]
+    fn example_answers_of_both_parts() {
+        assert_eq!(
+            example_answers(BOTH_PARTS_PAGE),
+            [
+                ("1abc2\npqr3stu8vwx\n".to_string(), Some("142".to_string())),
+                (
+                    "two1nine\neightwothree\n".to_string(),
+                    Some("281".to_string())
+                ),
+            ]
+        );
+    }
+
+    const TOO_SOON: &str = "You gave an answer too recently; you have to wait \
+        after submitting an answer before trying again.";
+
+    #[test]
+    fn wait_time_of_one_minute() {
+        let html = format!("{TOO_SOON} You have one minute left to wait.");
+        assert_eq!(wait_time(&html), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn wait_time_in_minutes_and_seconds() {
+        let html = format!("{TOO_SOON} You have 5m 30s left to wait.");
+        assert_eq!(wait_time(&html), Some(Duration::from_secs(330)));
+    }
+
+    #[test]
+    fn wait_time_missing() {
+        assert_eq!(wait_time(TOO_SOON), None);
+        assert_eq!(wait_time("That's the right answer!"), None);
+    }
+
+    #[test]
+    fn wait_retry_waits_with_a_margin() {
+        let wait = Some(Duration::from_secs(30));
+        assert_eq!(
+            wait_retry(wait, 0, 3, Duration::ZERO),
+            WaitRetry::After(Duration::from_secs(30) + WAIT_RETRY_MARGIN)
+        );
+    }
+
+    #[test]
+    fn wait_retry_stops() {
+        let wait = Some(Duration::from_secs(30));
+        assert_eq!(wait_retry(None, 0, 3, Duration::ZERO), WaitRetry::Stop);
+        assert_eq!(wait_retry(wait, 3, 3, Duration::ZERO), WaitRetry::Stop);
+        assert_eq!(wait_retry(wait, 0, 0, Duration::ZERO), WaitRetry::Stop);
+    }
+
+    #[test]
+    fn wait_retry_gives_up_past_the_limit() {
+        let wait = Some(Duration::from_secs(60));
+        let waited = MAX_WAIT_RETRY_TIME - Duration::from_secs(30);
+        assert_eq!(
+            wait_retry(wait, 1, 3, waited),
+            WaitRetry::TooLong(Duration::from_secs(60) + WAIT_RETRY_MARGIN)
+        );
+    }
+
+    const NO_MAIN_PAGE: &str = "<html><body>\
+        <p>Please don&#39;t repeatedly request this endpoint.</p>\
+        </body></html>";
+
+    #[test]
+    fn main_element_of_a_page() {
+        let client = builder().build().unwrap();
+        let html = "<html><body><main><article>Puzzle</article></main>\
+            </body></html>";
+        assert_eq!(
+            client.main_element(html).unwrap(),
+            "<article>Puzzle</article>"
+        );
+    }
+
+    #[test]
+    fn main_element_missing() {
+        let client = builder().build().unwrap();
+        assert!(matches!(
+            client.main_element(NO_MAIN_PAGE),
+            Err(AocError::MissingMainElement(snippet))
+                if snippet == "Please don't repeatedly request this endpoint."
+        ));
+    }
+
+    #[test]
+    fn main_element_falls_back_to_the_body() {
+        let client = builder().main_fallback(true).build().unwrap();
+        assert_eq!(
+            client.main_element(NO_MAIN_PAGE).unwrap(),
+            "<p>Please don&#39;t repeatedly request this endpoint.</p>"
+        );
+    }
+
+    #[test]
+    fn maintenance_pages() {
+        for html in [
+            "<title>Advent of Code is down for maintenance</title>",
+            "<p>The site is undergoing scheduled maintenance.</p>",
+            "<h1>503 Service Temporarily Unavailable</h1>",
+        ] {
+            assert!(is_maintenance_page(html), "{html}");
+        }
+        assert!(!is_maintenance_page(PART_ONE_PAGE));
+    }
+
+    #[test]
+    fn maintenance_page_without_main_element() {
+        let client = builder().main_fallback(true).build().unwrap();
+        let html = "<html><body><h1>Down for maintenance</h1></body></html>";
+        assert!(matches!(
+            client.main_element(html),
+            Err(AocError::ServiceUnavailable)
+        ));
+    }
+
+    #[test]
+    fn gateway_errors() {
+        for status in [
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+        ] {
+            assert!(is_gateway_error(status), "{status}");
+        }
+        for status in [
+            StatusCode::OK,
+            StatusCode::BAD_REQUEST,
+            StatusCode::NOT_FOUND,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            assert!(!is_gateway_error(status), "{status}");
+        }
+    }
+
+    #[test]
+    fn private_leaderboard_statuses() {
+        assert!(matches!(
+            private_leaderboard_error(StatusCode::FOUND, 1234),
+            Some(AocError::PrivateLeaderboardNotAvailable)
+        ));
+        assert!(matches!(
+            private_leaderboard_error(StatusCode::NOT_FOUND, 1234),
+            Some(AocError::PrivateLeaderboardNotFound(1234))
+        ));
+        assert!(private_leaderboard_error(StatusCode::OK, 1234).is_none());
+        // Left to check_status
+        assert!(private_leaderboard_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            1234
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn shared_cache_only_serves_the_first_part() {
+        let dir = env::temp_dir()
+            .join(format!("aoc-shared-cache-{}", std::process::id()));
+        let cached_file = FileLayout::YearDay
+            .directory(&dir, 2015, 1)
+            .join(MIRROR_PUZZLE_FILENAME);
+        create_dir_all(cached_file.parent().unwrap()).unwrap();
+        std::fs::write(&cached_file, BOTH_PARTS_PAGE).unwrap();
+
+        let client = builder().shared_cache_dir(&dir).build().unwrap();
+        let puzzle_html = client.get_puzzle_html();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let puzzle_html = puzzle_html.unwrap();
+        assert_eq!(puzzle_articles(&puzzle_html).len(), 1);
+        assert!(!puzzle_html.contains("Your puzzle answer was"));
+        assert!(!puzzle_html.contains("Part Two"));
+    }
+
+    #[test]
+    fn resubmits_when_the_answer_cannot_have_been_scored() {
+        assert!(may_resubmit(SubmitFailure::Connect));
+        for status in [
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+        ] {
+            let body = "<html><body><h1>502 Bad Gateway</h1></body></html>";
+            assert!(may_resubmit(SubmitFailure::Status(status, body)));
+        }
+    }
+
+    #[test]
+    fn never_resubmits_after_an_outcome_page() {
+        let outcome = "<html><body><main><article><p>That's not the right \
+            answer.</p></article></main></body></html>";
+        for status in [
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::GATEWAY_TIMEOUT,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            assert!(!may_resubmit(SubmitFailure::Status(status, outcome)));
+        }
+    }
+
+    #[test]
+    fn never_resubmits_after_other_failures() {
+        assert!(!may_resubmit(SubmitFailure::Request));
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::NOT_FOUND,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            assert!(!may_resubmit(SubmitFailure::Status(status, "Oops")));
+        }
+    }
+}
@@ -1,5 +1,12 @@
+use aoc_client::StarGlyphs;
 use aoc_client::{LeaderboardId, PuzzleDay, PuzzleYear};
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+// Ways of submitting that don't take the part and answer as arguments
+const SUBMIT_WITHOUT_PART: [&str; 4] = ["part1", "part2", "unsolved", "batch"];
 
 #[derive(Parser, Debug)]
 #[command(version, about, infer_subcommands = true)]
@@ -11,6 +18,10 @@ pub struct Args {
     #[arg(short, long, global = true)]
     pub day: Option<PuzzleDay>,
 
+    /// Use the latest day with at least one star (checks the calendar)
+    #[arg(long, global = true, conflicts_with = "day")]
+    pub latest_solved: bool,
+
     /// Puzzle year [default: year of current or last Advent of Code event]
     #[arg(short, long, global = true)]
     pub year: Option<PuzzleYear>,
@@ -19,7 +30,52 @@ pub struct Args {
     #[arg(short, long, alias = "session", global = true, value_name = "PATH")]
     pub session_file: Option<String>,
 
-    /// Width at which to wrap output [default: terminal width]
+    /// Command whose output is the session cookie
+    #[arg(
+        long,
+        global = true,
+        value_name = "COMMAND",
+        conflicts_with = "session_file"
+    )]
+    pub session_command: Option<String>,
+
+    /// Fail instead of warning if the session cookie file can be read by
+    /// other users
+    #[arg(long, global = true)]
+    pub strict_permissions: bool,
+
+    /// Fail early for puzzles that unlock more than a year from now
+    #[arg(long, global = true)]
+    pub strict_dates: bool,
+
+    /// Read puzzles and calendars without logging in
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["session_file", "session_command"]
+    )]
+    pub anonymous: bool,
+
+    /// Read puzzles and inputs from a local directory instead of the website
+    #[arg(long, global = true, value_name = "DIR")]
+    pub mirror: Option<String>,
+
+    /// Read-only directory with shared copies of puzzles, laid out as
+    /// <YEAR>/<DAY>/puzzle.html (only their first part is read)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub shared_cache: Option<String>,
+
+    /// Extra HTTP header to send with every request, e.g. for a proxy
+    #[arg(
+        short = 'H',
+        long,
+        global = true,
+        value_name = "NAME: VALUE",
+        value_parser = parse_header
+    )]
+    pub header: Vec<(String, String)>,
+
+    /// Width at which to wrap output [default: $COLUMNS or terminal width]
     #[arg(short, long, global = true)]
     pub width: Option<usize>,
 
@@ -42,7 +98,7 @@ pub struct Args {
     )]
     pub puzzle_only: bool,
 
-    /// Path where to save puzzle input
+    /// Path where to save puzzle input, or "-" for stdout
     #[arg(
         short,
         long,
@@ -53,7 +109,7 @@ pub struct Args {
     )]
     pub input_file: String,
 
-    /// Path where to save puzzle description
+    /// Path where to save puzzle description, or "-" for stdout
     #[arg(
         short,
         long,
@@ -64,16 +120,93 @@ pub struct Args {
     )]
     pub puzzle_file: String,
 
+    /// Name saved files after the puzzle title, e.g. day05-scratchcards.txt
+    /// and day05-scratchcards.md, in the directories of the paths above
+    #[arg(long, global = true)]
+    pub title_filenames: bool,
+
+    /// Width at which to wrap the saved puzzle description, or "off" to keep
+    /// paragraphs unwrapped
+    #[arg(
+        long,
+        global = true,
+        value_name = "off|WIDTH",
+        default_value = "off"
+    )]
+    pub md_wrap: MarkdownWrap,
+
+    /// Markdown flavor of the saved puzzle description
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = MdFlavor::Gfm
+    )]
+    pub md_flavor: MdFlavor,
+
+    /// Save the input gzip-compressed, to <INPUT_FILE>.gz
+    #[arg(long, global = true)]
+    pub compress: bool,
+
+    /// Also save a SHA-256 checksum of the input to <INPUT_FILE>.sha256
+    #[arg(long, global = true)]
+    pub checksum: bool,
+
     /// Show HTML markup including links
     #[arg(short = 'm', long, global = true)]
     pub show_html_markup: bool,
 
+    /// Marks for days with both stars, one star and no stars
+    #[arg(
+        long,
+        global = true,
+        value_name = "BOTH,FIRST,NONE",
+        value_parser = parse_star_glyphs
+    )]
+    pub star_glyphs: Option<StarGlyphs>,
+
+    /// Show emphasized puzzle text in bold or underlined
+    #[arg(long, global = true, conflicts_with = "show_html_markup")]
+    pub emphasis: bool,
+
+    /// Show calendar and leaderboards as plain text without decorations
+    /// (always the case when output is redirected)
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Hide the closing remarks of puzzles that are already complete
+    #[arg(long, global = true)]
+    pub concise: bool,
+
+    /// Use the whole page when a response has no <main> element, instead of
+    /// failing with a snippet of it
+    #[arg(long, global = true)]
+    pub main_fallback: bool,
+
+    /// Show how long each network request and conversion took
+    #[arg(long, global = true)]
+    pub timings: bool,
+
+    /// Don't log the tool's name and description at startup
+    #[arg(long, global = true)]
+    pub no_banner: bool,
+
     /// Restrict log messages to errors only
     #[arg(short, long, global = true)]
     pub quiet: bool,
 
-    /// Enable debug logging
-    #[arg(long, global = true, conflicts_with = "quiet")]
+    /// Increase logging verbosity (-vv for debug, -vvv for trace)
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = ArgAction::Count,
+        conflicts_with = "quiet"
+    )]
+    pub verbose: u8,
+
+    /// Enable debug logging (deprecated, use -vv instead)
+    #[arg(long, global = true, hide = true, conflicts_with = "quiet")]
     pub debug: bool,
 }
 
@@ -81,31 +214,325 @@ pub struct Args {
 pub enum Command {
     /// Show Advent of Code calendar and stars collected
     #[command(visible_alias = "c")]
-    Calendar,
+    Calendar {
+        /// Save the calendar as an SVG image of days coloured by stars
+        /// collected instead
+        #[arg(long, value_name = "PATH")]
+        image: Option<String>,
+    },
 
     /// Save puzzle description and input to files
     #[command(visible_alias = "d")]
-    Download,
+    Download {
+        /// Save puzzle descriptions (not inputs) of every day of these years
+        /// to <YEAR>/<DAY>/puzzle.md
+        #[arg(long, value_name = "FIRST-LAST", value_parser = parse_years)]
+        years: Option<RangeInclusive<PuzzleYear>>,
+
+        /// Save the puzzle, its examples and the input together to this
+        /// markdown file instead
+        #[arg(long, value_name = "PATH", conflicts_with = "years")]
+        bundle: Option<String>,
+
+        /// Print a one-line summary of saved files to stdout (the default)
+        #[arg(long, overrides_with = "no_summary")]
+        summary: bool,
+
+        /// Don't print the summary of saved files
+        #[arg(long, overrides_with = "summary")]
+        no_summary: bool,
+    },
+
+    /// Download puzzles and inputs of the year that aren't saved yet, to
+    /// <DIR>/<YEAR>/<DAY>/
+    Sync {
+        /// Directory with one subdirectory per year
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        dir: String,
+    },
 
     /// Read puzzle statement (the default command)
     #[command(visible_alias = "r")]
-    Read,
+    Read {
+        /// How to print the puzzle statement
+        #[arg(long, value_enum, default_value_t = ReadFormat::Text)]
+        format: ReadFormat,
+
+        /// Show only the statement of this puzzle part
+        #[arg(long, value_parser = ["1", "2"])]
+        part: Option<String>,
+
+        /// Show a puzzle saved as HTML instead of fetching it
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["part", "format"]
+        )]
+        html_file: Option<String>,
+
+        /// Show the puzzle in a pager ($AOC_PAGER, $PAGER or less) when
+        /// writing to a terminal
+        #[arg(long, conflicts_with = "format")]
+        pager: bool,
+
+        /// Show only the first lines of the puzzle statement
+        #[arg(
+            long,
+            value_name = "LINES",
+            num_args = 0..=1,
+            default_missing_value = "20",
+            conflicts_with = "format"
+        )]
+        head: Option<usize>,
+    },
 
     /// Submit puzzle answer
     #[command(visible_alias = "s")]
     Submit {
         /// Puzzle part
-        #[arg(value_parser = ["1", "2"])]
-        part: String,
+        #[arg(
+            value_parser = ["1", "2"],
+            required_unless_present_any = SUBMIT_WITHOUT_PART
+        )]
+        part: Option<String>,
 
         /// Puzzle answer
-        answer: String,
+        #[arg(required_unless_present_any = SUBMIT_WITHOUT_PART)]
+        answer: Option<String>,
+
+        /// Submit the answer to part 1
+        #[arg(
+            short = '1',
+            long,
+            value_name = "ANSWER",
+            conflicts_with_all = ["part", "part2", "unsolved", "batch"]
+        )]
+        part1: Option<String>,
+
+        /// Submit the answer to part 2
+        #[arg(
+            short = '2',
+            long,
+            value_name = "ANSWER",
+            conflicts_with_all = ["part", "unsolved", "batch"]
+        )]
+        part2: Option<String>,
+
+        /// Submit the answer to the first part you haven't solved yet
+        #[arg(
+            long,
+            value_name = "ANSWER",
+            conflicts_with_all = ["part", "batch"]
+        )]
+        unsolved: Option<String>,
+
+        /// Submit answers from a file with one "day,part,answer" per line
+        #[arg(long, value_name = "PATH", conflicts_with = "part")]
+        batch: Option<String>,
+
+        /// Remove a leading "Answer:" or "Part N:" label from answers (they
+        /// are always trimmed of surrounding whitespace)
+        #[arg(long)]
+        strip_label: bool,
+
+        /// When answering too soon, wait as long as asked to and resubmit,
+        /// up to this many times
+        #[arg(
+            long,
+            value_name = "ATTEMPTS",
+            num_args = 0..=1,
+            default_missing_value = "3"
+        )]
+        retry_on_wait: Option<u32>,
+
+        /// Report batch results in Test Anything Protocol (TAP) format
+        #[arg(long, requires = "batch")]
+        tap: bool,
+    },
+
+    /// Show the session cookie source, puzzle and files that would be used,
+    /// without going to the website
+    Resolve,
+
+    /// Print the puzzle, your answers and input as one JSON document
+    Export {
+        /// Output format
+        #[arg(long, required = true)]
+        json: bool,
+    },
+
+    /// Run a solution with the puzzle input and optionally submit its answer
+    Run {
+        /// Command that reads the puzzle input from stdin
+        #[arg(long, value_name = "COMMAND")]
+        cmd: String,
+
+        /// Submit the answer to this puzzle part
+        #[arg(long, value_name = "PART", value_parser = ["1", "2"])]
+        submit: Option<String>,
+
+        /// Use the whole output as answer instead of its last non-empty line
+        #[arg(long)]
+        full_output: bool,
+
+        /// Take the answer from the first match of this regex in the output
+        /// (its first capture group if it has one)
+        #[arg(long, value_name = "PATTERN", conflicts_with = "full_output")]
+        answer_regex: Option<Regex>,
+
+        /// Submit even if the part is already solved
+        #[arg(long, requires = "submit")]
+        force: bool,
     },
 
+    /// Wait for the puzzle to unlock and then read or download it
+    #[command(visible_alias = "w")]
+    Watch {
+        /// Download puzzle description and input instead of reading it
+        #[arg(long)]
+        download: bool,
+
+        /// How to show the time left until the puzzle unlocks
+        #[arg(long, value_enum, default_value_t = Countdown::Clock)]
+        countdown: Countdown,
+    },
+
+    /// Show the global leaderboard of a puzzle
+    #[command(visible_alias = "g")]
+    GlobalLeaderboard,
+
     /// Show the state of a private leaderboard
     #[command(visible_alias = "p")]
     PrivateLeaderboard {
         /// Private leaderboard ID
         leaderboard_id: LeaderboardId,
+
+        /// Print the leaderboard JSON exactly as returned by Advent of Code
+        #[arg(long)]
+        raw: bool,
+
+        /// Output format
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = LeaderboardFormat::Table,
+            conflicts_with = "raw"
+        )]
+        format: LeaderboardFormat,
+
+        /// With CSV, one row per star collected by each member instead of one
+        /// row per member
+        #[arg(long)]
+        full: bool,
+
+        /// How to order members with the same score
+        #[arg(long, value_enum, default_value_t = Tiebreak::Id)]
+        tiebreak: Tiebreak,
+
+        /// Keep showing the leaderboard, refreshing it periodically
+        #[arg(long, conflicts_with_all = ["raw", "format"])]
+        watch: bool,
+
+        /// Seconds between refreshes (at least 900, as Advent of Code asks)
+        #[arg(
+            long,
+            requires = "watch",
+            default_value_t = 900,
+            value_parser = clap::value_parser!(u64).range(900..)
+        )]
+        interval: u64,
     },
 }
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ReadFormat {
+    /// Rendered as plain text
+    Text,
+    /// The puzzle HTML, unconverted
+    RawHtml,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum MdFlavor {
+    /// CommonMark, with tables as code blocks
+    Common,
+    /// GitHub-Flavored Markdown, with tables and strikethrough
+    Gfm,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum MarkdownWrap {
+    Off,
+    Width(usize),
+}
+
+impl FromStr for MarkdownWrap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            _ => s
+                .parse()
+                .map(Self::Width)
+                .map_err(|_| format!("expected \"off\" or a width, got {s}")),
+        }
+    }
+}
+
+fn parse_years(s: &str) -> Result<RangeInclusive<PuzzleYear>, String> {
+    let (first, last) = s.split_once('-').unwrap_or((s, s));
+    match (first.parse(), last.parse()) {
+        (Ok(first), Ok(last)) if first <= last => Ok(first..=last),
+        _ => Err(format!(
+            "expected a year or a range like 2015-2022, got {s}"
+        )),
+    }
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((name, value)) => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        None => Err(format!("expected a header like \"NAME: VALUE\", got {s}")),
+    }
+}
+
+fn parse_star_glyphs(s: &str) -> Result<StarGlyphs, String> {
+    match s.split(',').collect::<Vec<_>>()[..] {
+        [both, first, none] => Ok(StarGlyphs {
+            both: both.to_string(),
+            first: first.to_string(),
+            none: none.to_string(),
+        }),
+        _ => Err(format!("expected three comma-separated marks, got {s}")),
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LeaderboardFormat {
+    /// Stars of each member as in the website
+    Table,
+    /// Comma-separated values with the columns member_id, name, local_score
+    /// and stars (and day, part and completed_at with --full)
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Countdown {
+    /// Hours, minutes and seconds, as in 01:02:03
+    Clock,
+    /// Only the units needed, as in 1h 2m 3s
+    Units,
+    /// A bar filling up from when waiting started, with the seconds left
+    Bar,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Tiebreak {
+    /// Lowest member ID first
+    Id,
+    /// Earliest to reach the score first
+    Time,
+}
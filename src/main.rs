@@ -1,21 +1,63 @@
 mod args;
 
-use aoc_client::{AocClient, AocError, AocResult};
-use args::{Args, Command};
+use aoc_client::{
+    last_unlocked_day, AocClient, AocClientBuilder, AocError, AocResult,
+    FileLayout, LeaderboardId, LeaderboardTiebreak, MarkdownFlavor,
+    OutputContext, PuzzleDay, PuzzlePart, PuzzleYear, SubmissionOutcome,
+};
+use args::{
+    Args, Command, Countdown, LeaderboardFormat, MarkdownWrap, MdFlavor,
+    ReadFormat, Tiebreak,
+};
 use clap::{crate_description, crate_name, Parser};
 use env_logger::{Builder, Env};
 use exit_code::*;
 use log::{error, info, warn, LevelFilter};
-use std::process::exit;
+use regex::Regex;
+use std::env;
+use std::fs::read_to_string;
+use std::io::{stderr, IsTerminal, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command as ProcessCommand, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+const PAGER_ENV_VAR: &str = "AOC_PAGER";
+const BATCH_WAIT_RETRIES: u32 = 3;
+// Pause between the puzzles of an archive, to go easy on the website
+const ARCHIVE_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_PAGER: &str = "less";
+const LESS_ENV_VAR: &str = "LESS";
 
 fn main() {
     let args = Args::parse();
 
     setup_log(&args);
 
-    info!("🎄 {} - {}", crate_name!(), crate_description!());
+    if !args.no_banner {
+        info!("🎄 {} - {}", crate_name!(), crate_description!());
+    }
+
+    // Detected once, so that colours and everything the client prints agree
+    let output = OutputContext::detect();
+    output.apply_color();
+    let result = match &args.command {
+        // Shown without a client, which can't be built when a setting is off
+        Some(Command::Resolve) => {
+            resolve(&args, output);
+            Ok(())
+        }
+        _ => build_client(&args, output).and_then(|client| {
+            let result = run(&args, &client);
+            if args.timings {
+                show_timings(&client);
+            }
+            result
+        }),
+    };
 
-    match build_client(&args).and_then(|client| run(&args, client)) {
+    match result {
         Ok(_) => exit(SUCCESS),
         Err(err) => {
             error!("🔔 {err}");
@@ -24,16 +66,30 @@ fn main() {
                 AocError::InvalidEventYear(..) => USAGE_ERROR,
                 AocError::InvalidPuzzleDay(..) => USAGE_ERROR,
                 AocError::LockedPuzzle(..) => USAGE_ERROR,
+                AocError::DistantPuzzle(..) => USAGE_ERROR,
                 AocError::SessionFileNotFound => NO_INPUT,
                 AocError::SessionFileReadError { .. } => IO_ERROR,
-                AocError::InvalidSessionCookie { .. } => DATA_ERROR,
+                AocError::SessionCommandError { .. } => IO_ERROR,
+                AocError::SessionCookieRequired => USAGE_ERROR,
+                AocError::InvalidSessionCookie => DATA_ERROR,
+                AocError::InsecureSessionFile(..) => NO_PERMISSION,
+                AocError::SessionExpired => NO_PERMISSION,
+                AocError::InvalidHeader(..) => USAGE_ERROR,
                 AocError::HttpRequestError { .. } => FAILURE,
                 AocError::AocResponseError => FAILURE,
+                AocError::MissingMainElement(..) => FAILURE,
+                AocError::ServiceUnavailable => SERVICE_UNAVAILABLE,
                 AocError::PrivateLeaderboardNotAvailable => FAILURE,
+                AocError::PrivateLeaderboardNotFound(..) => USAGE_ERROR,
+                AocError::FileReadError { .. } => NO_INPUT,
                 AocError::FileWriteError { .. } => CANNOT_CREATE,
                 AocError::ClientFieldMissing(..) => USAGE_ERROR,
                 AocError::InvalidPuzzlePart => USAGE_ERROR,
+                AocError::PuzzlePartLocked(..) => USAGE_ERROR,
                 AocError::InvalidOutputWidth => USAGE_ERROR,
+                AocError::SolutionCommandError { .. } => IO_ERROR,
+                AocError::AnswerNotFound => DATA_ERROR,
+                AocError::InvalidBatchLine(..) => DATA_ERROR,
             };
 
             if exit_code == FAILURE {
@@ -54,61 +110,773 @@ fn setup_log(args: &Args) {
     let mut log_builder =
         Builder::from_env(Env::default().default_filter_or("info"));
 
-    if args.quiet {
-        log_builder.filter_module("aoc", LevelFilter::Error);
-    } else if args.debug {
-        log_builder.filter_module("aoc", LevelFilter::Debug);
+    let level = match (args.quiet, args.debug, args.verbose) {
+        (true, _, _) => Some(LevelFilter::Error),
+        (_, _, 3..) => Some(LevelFilter::Trace),
+        (_, true, _) | (_, _, 2) => Some(LevelFilter::Debug),
+        _ => None,
+    };
+
+    if let Some(level) = level {
+        log_builder.filter_module("aoc", level);
     }
 
     log_builder.format_timestamp(None).init();
 }
 
-fn build_client(args: &Args) -> AocResult<AocClient> {
+fn build_client(args: &Args, output: OutputContext) -> AocResult<AocClient> {
+    client_builder(args, output, args.year, args.day)?.build()
+}
+
+fn client_builder(
+    args: &Args,
+    output: OutputContext,
+    year: Option<PuzzleYear>,
+    day: Option<PuzzleDay>,
+) -> AocResult<AocClientBuilder> {
     let mut builder = AocClient::builder();
+    builder.strict_session_permissions(args.strict_permissions);
+    session_cookie(args, &mut builder)?;
+    puzzle_date(&mut builder, year, day)?;
+
+    builder.output_context(output);
+    if let Some(width) = args.width {
+        builder.output_width(width)?;
+    }
+
+    if let Some(glyphs) = &args.star_glyphs {
+        builder.star_glyphs(glyphs.clone());
+    }
+
+    if let MarkdownWrap::Width(width) = args.md_wrap {
+        builder.markdown_width(Some(width))?;
+    }
+
+    builder.markdown_flavor(match args.md_flavor {
+        MdFlavor::Common => MarkdownFlavor::CommonMark,
+        MdFlavor::Gfm => MarkdownFlavor::Gfm,
+    });
+
+    if let Some(Command::PrivateLeaderboard { tiebreak, .. }) = &args.command {
+        builder.leaderboard_tiebreak(match tiebreak {
+            Tiebreak::Id => LeaderboardTiebreak::Id,
+            Tiebreak::Time => LeaderboardTiebreak::Time,
+        });
+    }
+
+    if let Some(dir) = &args.mirror {
+        builder.mirror_dir(dir);
+    }
 
-    if let Some(file) = &args.session_file {
+    for (name, value) in &args.header {
+        builder.extra_header(name, value)?;
+    }
+
+    if let Some(dir) = &args.shared_cache {
+        builder.shared_cache_dir(dir);
+    }
+
+    if let Some(Command::Submit {
+        strip_label,
+        retry_on_wait,
+        batch,
+        ..
+    }) = &args.command
+    {
+        // Batches always wait when asked to, to pace the answers after it
+        let default_retries = match batch {
+            Some(_) => BATCH_WAIT_RETRIES,
+            None => 0,
+        };
+        builder
+            .strip_answer_label(*strip_label)
+            .wait_retries(retry_on_wait.unwrap_or(default_retries));
+    }
+
+    builder
+        .input_filename(&args.input_file)
+        .puzzle_filename(&args.puzzle_file)
+        .overwrite_files(args.overwrite)
+        .input_checksum(args.checksum)
+        .compress_input(args.compress)
+        .show_html_markup(args.show_html_markup)
+        .emphasis(args.emphasis)
+        .concise(args.concise)
+        // Decorations are only for terminals, like colours
+        .plain(args.plain || !output.is_terminal)
+        .main_fallback(args.main_fallback)
+        .reject_distant_puzzles(args.strict_dates);
+
+    // Resolved last since it fetches the calendar with the settings above
+    if args.latest_solved && day.is_none() {
+        builder.latest_solved_day()?;
+    }
+
+    Ok(builder)
+}
+
+fn session_cookie(
+    args: &Args,
+    builder: &mut AocClientBuilder,
+) -> AocResult<()> {
+    if args.anonymous {
+        builder.anonymous(true);
+    } else if let Some(file) = &args.session_file {
         builder.session_cookie_from_file(file)?;
+    } else if let Some(command) = &args.session_command {
+        builder.session_cookie_from_command(command)?;
     } else {
         builder.session_cookie_from_default_locations()?;
     }
+    Ok(())
+}
 
-    match (args.year, args.day) {
+fn puzzle_date(
+    builder: &mut AocClientBuilder,
+    year: Option<PuzzleYear>,
+    day: Option<PuzzleDay>,
+) -> AocResult<()> {
+    match (year, day) {
         (Some(year), Some(day)) => builder.year(year)?.day(day)?,
         (Some(year), None) => builder.year(year)?.latest_puzzle_day()?,
         (None, Some(day)) => builder.latest_event_year()?.day(day)?,
         (None, None) => builder.latest_puzzle_day()?,
     };
+    Ok(())
+}
 
-    if let Some(width) = args.width {
-        builder.output_width(width)?;
+fn run(args: &Args, client: &AocClient) -> AocResult<()> {
+    match &args.command {
+        Some(Command::Calendar { image: Some(path) }) => {
+            client.save_calendar_svg(path)
+        }
+        Some(Command::Calendar { image: None }) => client.show_calendar(),
+        Some(Command::Download {
+            years: Some(years), ..
+        }) => download_puzzles(args, client, years.clone()),
+        Some(Command::Download {
+            bundle: Some(path), ..
+        }) => client.save_bundle(path),
+        Some(Command::Download { no_summary, .. }) => {
+            download(args, client, !no_summary)
+        }
+        Some(Command::Sync { dir }) => sync(args, client, dir),
+        Some(Command::Read {
+            format: ReadFormat::Text,
+            part,
+            html_file,
+            pager,
+            head,
+        }) if *pager || head.is_some() => {
+            let mut text = match (html_file, part) {
+                (Some(path), _) => client.get_html_file_text(path)?,
+                (None, Some(part)) => client.get_puzzle_part_text(part)?,
+                (None, None) => client.get_puzzle_text()?,
+            };
+            if let Some(lines) = head {
+                text = head_lines(&text, *lines);
+            }
+
+            if *pager {
+                show_in_pager(&text, client.output_context());
+            } else {
+                println!("\n{text}");
+            }
+            Ok(())
+        }
+        Some(Command::Read {
+            html_file: Some(path),
+            ..
+        }) => {
+            println!("\n{}", client.get_html_file_text(path)?);
+            Ok(())
+        }
+        Some(Command::Read {
+            format: ReadFormat::RawHtml,
+            part: None,
+            ..
+        }) => {
+            println!("{}", client.get_puzzle_html()?);
+            Ok(())
+        }
+        Some(Command::Read {
+            format: ReadFormat::RawHtml,
+            part: Some(part),
+            ..
+        }) => {
+            println!("{}", client.get_puzzle_part_html(part)?);
+            Ok(())
+        }
+        Some(Command::Read {
+            part: Some(part), ..
+        }) => client.show_puzzle_part(part),
+        Some(Command::Submit {
+            batch: Some(path),
+            tap,
+            ..
+        }) => submit_batch(client, path, *tap),
+        Some(Command::Submit {
+            part1: Some(answer),
+            ..
+        }) => client.submit_answer_and_show_outcome("1", answer),
+        Some(Command::Submit {
+            part2: Some(answer),
+            ..
+        }) => client.submit_answer_and_show_outcome("2", answer),
+        Some(Command::Submit {
+            unsolved: Some(answer),
+            ..
+        }) => submit_to_unsolved_part(client, answer),
+        Some(Command::Submit {
+            part: Some(part),
+            answer: Some(answer),
+            ..
+        }) => client.submit_answer_and_show_outcome(part, answer),
+        Some(Command::Export { .. }) => {
+            println!("{}", client.export_json()?);
+            Ok(())
+        }
+        Some(Command::Run {
+            cmd,
+            submit,
+            full_output,
+            answer_regex,
+            force,
+        }) => run_solution(
+            client,
+            cmd,
+            submit.as_deref(),
+            *full_output,
+            answer_regex.as_ref(),
+            *force,
+        ),
+        Some(Command::Watch {
+            download: true,
+            countdown,
+        }) => {
+            wait_for_unlock(args, client, *countdown);
+            download(args, client, false)
+        }
+        Some(Command::Watch {
+            download: false,
+            countdown,
+        }) => {
+            wait_for_unlock(args, client, *countdown);
+            client.show_puzzle()
+        }
+        Some(Command::GlobalLeaderboard) => client.show_global_leaderboard(),
+        Some(Command::PrivateLeaderboard {
+            leaderboard_id,
+            raw: true,
+            ..
+        }) => {
+            println!(
+                "{}",
+                client.get_private_leaderboard_json(*leaderboard_id)?
+            );
+            Ok(())
+        }
+        Some(Command::PrivateLeaderboard {
+            leaderboard_id,
+            format: LeaderboardFormat::Csv,
+            full,
+            ..
+        }) => {
+            print!(
+                "{}",
+                client.get_private_leaderboard_csv(*leaderboard_id, *full)?
+            );
+            Ok(())
+        }
+        Some(Command::PrivateLeaderboard {
+            leaderboard_id,
+            watch: true,
+            interval,
+            ..
+        }) => watch_private_leaderboard(client, *leaderboard_id, *interval),
+        Some(Command::PrivateLeaderboard { leaderboard_id, .. }) => {
+            client.show_private_leaderboard(*leaderboard_id)
+        }
+        _ => client.show_puzzle(),
     }
+}
 
+// Each setting is resolved on its own, so that one which can't be doesn't
+// hide the others, and nothing is fetched from the website
+fn resolve(args: &Args, output: OutputContext) {
+    let mut builder = AocClient::builder();
     builder
+        .strict_session_permissions(args.strict_permissions)
+        .output_context(output)
         .input_filename(&args.input_file)
         .puzzle_filename(&args.puzzle_file)
-        .overwrite_files(args.overwrite)
-        .show_html_markup(args.show_html_markup)
-        .build()
+        .compress_input(args.compress);
+
+    match session_cookie(args, &mut builder) {
+        Ok(()) => println!(
+            "session cookie: {}",
+            builder
+                .session_cookie_source()
+                .unwrap_or("none (anonymous)")
+        ),
+        Err(err) => println!("session cookie: unresolved ({err})"),
+    }
+
+    let unresolved = match puzzle_date(&mut builder, args.year, args.day) {
+        Ok(()) => String::new(),
+        Err(err) => format!("unresolved ({err})"),
+    };
+    match builder.resolved_year() {
+        Some(year) => println!("year: {year}"),
+        None => println!("year: {unresolved}"),
+    }
+    match builder.resolved_day() {
+        _ if args.latest_solved && args.day.is_none() => {
+            println!(
+                "day: latest solved (not looked up, it needs the calendar)"
+            )
+        }
+        Some(day) => println!("day: {day}"),
+        None => println!("day: {unresolved}"),
+    }
+
+    match args.width.map(|width| builder.output_width(width).err()) {
+        Some(Some(err)) => println!("output width: unresolved ({err})"),
+        _ => println!("output width: {}", builder.resolved_output_width()),
+    }
+
+    println!("input file: {}", builder.saved_input_filename().display());
+    println!(
+        "puzzle file: {}",
+        builder.resolved_puzzle_filename().display()
+    );
 }
 
-fn run(args: &Args, client: AocClient) -> AocResult<()> {
-    match &args.command {
-        Some(Command::Calendar) => client.show_calendar(),
-        Some(Command::Download) => {
-            if !args.input_only {
-                client.save_puzzle_markdown()?;
+fn submit_to_unsolved_part(client: &AocClient, answer: &str) -> AocResult<()> {
+    let part = if client.solved_parts()?.contains(&PuzzlePart::PartOne) {
+        "2"
+    } else {
+        "1"
+    };
+
+    info!("🧮 Submitting answer to part {part}");
+    client.submit_answer_and_show_outcome(part, answer)
+}
+
+// Being asked to wait is answered by waiting (see --retry-on-wait), so that
+// the rest of the batch goes in at the pace the website allows
+fn submit_batch(client: &AocClient, path: &str, tap: bool) -> AocResult<()> {
+    let contents =
+        read_to_string(path).map_err(|err| AocError::FileReadError {
+            filename: path.to_string(),
+            source: err,
+        })?;
+
+    let mut submitted = 0;
+    let mut first_error = None;
+    for (line, line_number) in contents.lines().zip(1..) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        submitted += 1;
+
+        // Keep going so that one bad line doesn't hold back the others
+        let result = parse_batch_line(line, line_number).and_then(
+            |(day, part, answer)| {
+                let outcome = client.submit_answer_for(
+                    client.year(),
+                    day,
+                    part.to_string().as_str(),
+                    answer,
+                )?;
+                Ok((day, part, answer, outcome))
+            },
+        );
+        let (day, part, answer, outcome) = match result {
+            Ok(submission) => submission,
+            Err(err) => {
+                if tap {
+                    println!("not ok {submitted} - line {line_number}");
+                    println!("# {err}");
+                } else {
+                    error!("🔔 Line {line_number}: {err}");
+                }
+                first_error.get_or_insert(err);
+                continue;
             }
-            if !args.puzzle_only {
-                client.save_input()?;
+        };
+
+        if !tap {
+            println!("Day {day}, part {part}, answer {answer}: {outcome}");
+        } else if matches!(outcome, SubmissionOutcome::Correct) {
+            println!("ok {submitted} - day {day} part {part}");
+        } else {
+            println!("not ok {submitted} - day {day} part {part}");
+            println!("# answer {answer}: {outcome}");
+        }
+
+        if matches!(outcome, SubmissionOutcome::Wait) {
+            warn!("⏳ Still too soon at line {line_number}, try again later");
+            if tap {
+                println!("Bail out! Submitted too soon");
             }
-            Ok(())
+            break;
         }
-        Some(Command::Submit { part, answer }) => {
+    }
+
+    // The plan may come last since the number of answers isn't known upfront
+    if tap {
+        println!("1..{submitted}");
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+fn parse_batch_line(
+    line: &str,
+    line_number: usize,
+) -> AocResult<(PuzzleDay, PuzzlePart, &str)> {
+    let parse = || {
+        let mut fields = line.splitn(3, ',').map(str::trim);
+        let day = fields.next()?.parse().ok()?;
+        let part = PuzzlePart::try_from(fields.next()?).ok()?;
+        let answer = fields.next().filter(|answer| !answer.is_empty())?;
+        Some((day, part, answer))
+    };
+    parse().ok_or(AocError::InvalidBatchLine(line_number))
+}
+
+fn run_solution(
+    client: &AocClient,
+    cmd: &str,
+    submit: Option<&str>,
+    full_output: bool,
+    answer_regex: Option<&Regex>,
+    force: bool,
+) -> AocResult<()> {
+    let output = client.run_solution(cmd)?;
+    let answer = if let Some(regex) = answer_regex {
+        regex
+            .captures(&output)
+            .and_then(|captures| captures.get(1).or(captures.get(0)))
+            .map(|answer| answer.as_str().trim())
+            .ok_or(AocError::AnswerNotFound)?
+    } else if full_output {
+        output.trim()
+    } else {
+        output
+            .lines()
+            .map(str::trim)
+            .rev()
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+    };
+
+    if answer.is_empty() {
+        return Err(AocError::AnswerNotFound);
+    }
+
+    match submit {
+        Some(part) => {
+            // Spare a request when rerunning a solution that's already right
+            let index = if part == "1" { 0 } else { 1 };
+            let my_answers = if force {
+                Vec::new()
+            } else {
+                client.get_my_answers()?
+            };
+            if let Some(my_answer) = my_answers.get(index) {
+                if my_answer == answer {
+                    info!("🧮 Part {part} is already solved with {answer}");
+                } else {
+                    warn!(
+                        "🧮 Part {part} is already solved with {my_answer}, \
+                        not {answer} (use --force to submit anyway)"
+                    );
+                }
+                return Ok(());
+            }
+
+            info!("🧮 Submitting answer: {answer}");
             client.submit_answer_and_show_outcome(part, answer)
         }
-        Some(Command::PrivateLeaderboard { leaderboard_id }) => {
-            client.show_private_leaderboard(*leaderboard_id)
+        None => {
+            println!("{answer}");
+            Ok(())
+        }
+    }
+}
+
+fn head_lines(text: &str, lines: usize) -> String {
+    let total = text.lines().count();
+    let mut head = text.lines().take(lines).collect::<Vec<_>>().join("\n");
+    if total > lines {
+        head.push_str(&format!("\n... ({} more lines)", total - lines));
+    }
+    head
+}
+
+fn show_in_pager(text: &str, output: OutputContext) {
+    let pager = env::var(PAGER_ENV_VAR)
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut pager_args = pager.split_whitespace();
+
+    // Print directly if output is redirected or the pager can't be started
+    let child =
+        pager_args
+            .next()
+            .filter(|_| output.is_terminal)
+            .and_then(|program| {
+                let mut command = ProcessCommand::new(program);
+                // Like git, let less show colours unless told otherwise
+                if program == DEFAULT_PAGER
+                    && env::var_os(LESS_ENV_VAR).is_none()
+                {
+                    command.env(LESS_ENV_VAR, "R");
+                }
+                command.args(pager_args).stdin(Stdio::piped()).spawn().ok()
+            });
+    let Some(mut child) = child else {
+        println!("\n{text}");
+        return;
+    };
+
+    // Writing fails if the pager is closed before reading everything
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let _ = child.wait();
+}
+
+fn show_timings(client: &AocClient) {
+    for (operation, duration) in client.timings() {
+        eprintln!("⏱️  {operation}: {} ms", duration.as_millis());
+    }
+}
+
+fn download(args: &Args, client: &AocClient, summary: bool) -> AocResult<()> {
+    let (year, day) = (client.year(), client.day());
+    let (puzzle_file, input_file) = if args.title_filenames {
+        title_filenames(args, client)?
+    } else {
+        (
+            client.puzzle_filename().to_path_buf(),
+            client.input_filename().to_path_buf(),
+        )
+    };
+
+    let puzzle = if args.input_only {
+        "skipped"
+    } else {
+        client.save_puzzle_markdown_for(year, day, puzzle_file)?;
+        "saved"
+    };
+    let input = if args.puzzle_only {
+        "skipped"
+    } else {
+        client.save_input_for(year, day, input_file)?;
+        "saved"
+    };
+
+    // Printed to stdout, even with --quiet, for scripts to check, unless
+    // stdout is taken by the files themselves
+    let to_stdout = args.input_file == "-" || args.puzzle_file == "-";
+    if summary && !to_stdout {
+        println!("{year}/{day:02} input ({input}) puzzle ({puzzle})");
+    }
+
+    Ok(())
+}
+
+// Puzzle and input files named after the puzzle title, in the directories
+// of --puzzle-file and --input-file
+fn title_filenames(
+    args: &Args,
+    client: &AocClient,
+) -> AocResult<(PathBuf, PathBuf)> {
+    let stem = client.get_title_filename_stem()?;
+    let dir = |path: &str| {
+        Path::new(path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf()
+    };
+
+    Ok((
+        dir(&args.puzzle_file).join(format!("{stem}.md")),
+        dir(&args.input_file).join(format!("{stem}.txt")),
+    ))
+}
+
+fn download_puzzles(
+    args: &Args,
+    client: &AocClient,
+    years: RangeInclusive<PuzzleYear>,
+) -> AocResult<()> {
+    let (mut fetched, mut skipped) = (0, 0);
+    let mut first_error = None;
+
+    // Each puzzle goes in <dir>/<year>/<day>/<name> after --puzzle-file,
+    // unless they're all printed to stdout
+    let path = Path::new(&args.puzzle_file);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let name = path.file_name().unwrap_or_default();
+    let to_stdout = args.puzzle_file == "-";
+
+    for year in years {
+        let Some(last_day) = last_unlocked_day(year) else {
+            warn!("🔔 Skipping {year}, no puzzles have been unlocked");
+            continue;
+        };
+
+        // Saved puzzles are skipped so that an interrupted run can be resumed
+        for day in 1..=last_day {
+            let puzzle_file = match to_stdout {
+                true => path.to_path_buf(),
+                false => {
+                    FileLayout::YearDay.directory(dir, year, day).join(name)
+                }
+            };
+            if !to_stdout && puzzle_file.exists() && !args.overwrite {
+                skipped += 1;
+                continue;
+            }
+            if fetched > 0 || first_error.is_some() {
+                sleep(ARCHIVE_DELAY);
+            }
+            match client.save_puzzle_markdown_for(year, day, &puzzle_file) {
+                Ok(()) => fetched += 1,
+                Err(err) => {
+                    error!("🔔 Puzzle {day} of {year}: {err}");
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+    }
+
+    info!("🎅 Fetched {fetched} puzzles, skipped {skipped} already saved");
+    first_error.map_or(Ok(()), Err)
+}
+
+fn sync(args: &Args, client: &AocClient, dir: &str) -> AocResult<()> {
+    let year = client.year();
+    let last_day = last_unlocked_day(year).unwrap_or_default();
+    let (mut fetched, mut skipped) = (0, 0);
+
+    for day in 1..=last_day {
+        let mut sync_file =
+            |kind: &str, filename: &Path, save: &dyn Fn() -> AocResult<()>| {
+                if filename.exists() {
+                    skipped += 1;
+                    println!("{year}/{day:02} {kind} (skipped)");
+                    return;
+                }
+
+                match save() {
+                    Ok(()) => {
+                        fetched += 1;
+                        println!("{year}/{day:02} {kind} (fetched)");
+                    }
+                    Err(err) => error!("🔔 Puzzle {day} of {year}: {err}"),
+                }
+            };
+
+        if !args.puzzle_only {
+            let input_file = FileLayout::YearDay.input_filename(dir, year, day);
+            sync_file("input", &client.saved_input_path(&input_file), &|| {
+                client.save_input_for(year, day, &input_file)
+            });
+        }
+        if !args.input_only {
+            let puzzle_file =
+                FileLayout::YearDay.puzzle_filename(dir, year, day);
+            sync_file("puzzle", &puzzle_file, &|| {
+                client.save_puzzle_markdown_for(year, day, &puzzle_file)
+            });
+        }
+    }
+
+    info!("🎅 Fetched {fetched} files, skipped {skipped} already saved");
+    Ok(())
+}
+
+fn watch_private_leaderboard(
+    client: &AocClient,
+    leaderboard_id: LeaderboardId,
+    interval: u64,
+) -> AocResult<()> {
+    loop {
+        if client.output_context().is_terminal {
+            // Clear the screen and move the cursor to the top
+            print!("\x1b[2J\x1b[H");
+        } else {
+            // Keep updates apart in logs and files instead
+            println!("{}", "-".repeat(client.output_width()));
+        }
+        client.show_private_leaderboard(leaderboard_id)?;
+        sleep(Duration::from_secs(interval));
+    }
+}
+
+fn wait_for_unlock(args: &Args, client: &AocClient, countdown: Countdown) {
+    let Some(total) = client.time_until_unlock() else {
+        return;
+    };
+
+    info!(
+        "⏳ Waiting for puzzle {} of {} to unlock",
+        client.day(),
+        client.year()
+    );
+
+    // Drawn in place, so only in a terminal; a log gets just the line above
+    let show_countdown = !args.quiet && stderr().is_terminal();
+    while let Some(remaining) = client.time_until_unlock() {
+        if show_countdown {
+            let shown = format_countdown(countdown, remaining, total);
+            // Clear the line as the countdown may have gotten shorter
+            eprint!("\r\x1b[K⏳ {shown} ");
+            let _ = stderr().flush();
+        }
+        sleep(remaining.min(Duration::from_secs(1)));
+    }
+
+    if show_countdown {
+        eprintln!();
+    }
+}
+
+fn format_countdown(
+    countdown: Countdown,
+    remaining: Duration,
+    total: Duration,
+) -> String {
+    let secs = remaining.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    match countdown {
+        Countdown::Clock => format!("{hours:02}:{minutes:02}:{seconds:02}"),
+        Countdown::Units if hours > 0 => {
+            format!("{hours}h {minutes}m {seconds}s")
+        }
+        Countdown::Units if minutes > 0 => format!("{minutes}m {seconds}s"),
+        Countdown::Units => format!("{seconds}s"),
+        Countdown::Bar => {
+            let width = 30;
+            let filled = if total.is_zero() {
+                width
+            } else {
+                let elapsed = total.saturating_sub(remaining);
+                (elapsed.as_secs_f64() / total.as_secs_f64() * width as f64)
+                    as usize
+            };
+            format!(
+                "[{}{}] {secs}s",
+                "#".repeat(filled.min(width)),
+                "-".repeat(width - filled.min(width)),
+            )
         }
-        _ => client.show_puzzle(),
     }
 }